@@ -1,11 +1,18 @@
 use anyhow::Result;
-use atom_syndication::{ContentBuilder, Entry, EntryBuilder, LinkBuilder, Person};
+use atom_syndication::{CategoryBuilder, ContentBuilder, Entry, EntryBuilder, LinkBuilder, Person};
+use lazy_static::lazy_static;
+use regex::Regex;
 use reqwest::blocking::Client;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 
 use crate::common::*;
 
+mod archive;
+mod public_feed;
+
+pub use archive::load_archive;
+
 // Parsed from Blogger API endpoint
 #[derive(Serialize, Deserialize, Debug)]
 struct BloggerJson {
@@ -29,6 +36,19 @@ struct BloggerBlog<'a> {
 }
 
 pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str) -> Result<Box<dyn Blog + 'a>> {
+    // Public blogs can still be scraped without an API key, albeit more slowly.
+    if config.blogger_api_key.is_empty() {
+        return public_feed::get_blog(config, client, url);
+    }
+    get_api_blog(config, client, url).or_else(|api_err| {
+        public_feed::get_blog(config, client, url)
+            .map_err(|e| anyhow::anyhow!("{e}, and the Blogger API failed: {api_err}"))
+    })
+}
+
+fn get_api_blog<'a>(config: &'a Config, client: &'a Client, url: &str)
+    -> Result<Box<dyn Blog + 'a>>
+{
     let api_url = Url::parse("https://www.googleapis.com/blogger/v3/blogs/byurl")?;
     let api_json: BloggerJson = retry_request(config, || {
         Ok(client
@@ -84,17 +104,23 @@ impl Blog for BloggerBlog<'_> {
             r#"Scraping "{}" ({} posts, {} pages)"#,
             &self.api_json.name, self.api_json.posts.total_items, self.api_json.pages.total_items
         );
-        if self.api_json.posts.total_items > 0 {
+        for (url, summary) in &[
+            (&self.posts_api_url, &self.api_json.posts),
+            (&self.pages_api_url, &self.api_json.pages),
+        ] {
+            if summary.total_items == 0 {
+                continue;
+            }
             let mut next_page_token: Option<String> = None;
-            let pb = init_progress_bar(self.api_json.posts.total_items as u64);
+            let pb = init_progress_bar(summary.total_items as u64);
             loop {
-                let mut post_resp = retry_request(self.config, || {
-                    self.query_once(&self.posts_api_url, next_page_token.as_ref())
+                let mut resp = retry_request(self.config, || {
+                    self.query_once(url, next_page_token.as_ref())
                 })?;
-                pb.inc(post_resp.items.len().try_into().unwrap());
-                posts.extend(post_resp.items.iter().map(|p| self.post_to_entry(p)));
+                pb.inc(resp.items.len().try_into().unwrap());
+                posts.extend(resp.items.iter().map(|p| post_to_entry(&self.feed_id, p)));
 
-                next_page_token = post_resp.next_page_token.take();
+                next_page_token = resp.next_page_token.take();
                 if next_page_token.is_none() {
                     break;
                 }
@@ -104,16 +130,43 @@ impl Blog for BloggerBlog<'_> {
             pb.finish()
         }
 
-        if self.api_json.pages.total_items > 0 {
-            let page_resp = retry_request(self.config, || {
-                self.query_once(&self.pages_api_url, None)
-            })?;
-            posts.extend(page_resp.items.iter().map(|p| self.post_to_entry(p)));
-        }
+        Ok(posts)
+    }
 
-        // TODO: check posts.len == blog.pages.total_items + blog.posts.total_items
+    fn expected_len(&self) -> Option<usize> {
+        Some((self.api_json.posts.total_items + self.api_json.pages.total_items) as usize)
+    }
 
-        Ok(posts)
+    fn supports_comments(&self) -> bool {
+        true
+    }
+
+    fn comments(&self, entry: &Entry) -> Result<Vec<Comment>> {
+        let mut comments = Vec::new();
+        let Some(post_id) = post_id_from_entry(&self.feed_id, entry) else {
+            return Ok(comments);
+        };
+        let url = Url::parse(&format!("{}/{}/comments", self.posts_api_url, post_id))?;
+        let mut next_page_token: Option<String> = None;
+        loop {
+            let mut resp = match retry_request(self.config, || {
+                self.list_comments_once(&url, next_page_token.as_ref())
+            }) {
+                // Pages share the post ID space but can't be commented on.
+                Err(e) if is_not_found(&e) => break,
+                resp => resp?,
+            };
+            comments.extend(resp.items.iter().map(BloggerComment::to_comment));
+
+            next_page_token = resp.next_page_token.take();
+            if next_page_token.is_none() {
+                break;
+            }
+
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+
+        Ok(comments)
     }
 }
 
@@ -123,6 +176,7 @@ impl BloggerBlog<'_> {
             ("key", &self.config.blogger_api_key),
             ("orderBy", &String::from("published")),
             ("fetchBodies", &String::from("true")),
+            ("fetchImages", &String::from("true")),
         ]);
 
         let req = if let Some(token) = page_token {
@@ -136,32 +190,77 @@ impl BloggerBlog<'_> {
         Ok(resp.error_for_status()?.json()?)
     }
 
-    fn post_to_entry(&self, post: &Post) -> Entry {
-        let content = post.content.as_ref().map(|v| {
-            ContentBuilder::default()
-                .value(v.clone())
-                .content_type(Some("html".to_string()))
-                .build()
-        });
-
-        EntryBuilder::default()
-            .title(post.title.clone())
-            .id(format!("{}/{}", self.feed_id, post.id))
-            .published(parse_datetime(&post.published))
-            .author(Person {
-                name: post.author.display_name.clone(),
-                email: None,
-                uri: Some(post.author.url.clone()),
-            })
-            .content(content)
-            .link(
-                LinkBuilder::default()
-                    .href(post.url.clone())
-                    .rel("alternate")
-                    .build(),
-            )
+    fn list_comments_once(&self, api_url: &Url, page_token: Option<&String>)
+        -> Result<ListCommentsResponse>
+    {
+        let req = self.client.get(api_url.clone()).query(&[
+            ("key", &self.config.blogger_api_key),
+            ("maxResults", &String::from("500")),
+        ]);
+
+        let req = if let Some(token) = page_token {
+            req.query(&[("pageToken", token)])
+        } else {
+            req
+        };
+
+        Ok(req.send()?.error_for_status()?.json()?)
+    }
+}
+
+fn is_not_found(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .and_then(|re| re.status())
+        .is_some_and(|s| s == reqwest::StatusCode::NOT_FOUND)
+}
+
+// Extracts the numeric post or page ID from a Blogger Atom ID such as
+// "tag:blogger.com,1999:blog-123.post-456".
+fn post_id_from_tag(id: &str) -> Option<&str> {
+    lazy_static! {
+        static ref POST_ID: Regex = Regex::new(r"\.(?:post|page)-(\d+)$").unwrap();
+    };
+    POST_ID.captures(id).and_then(|c| c.get(1)).map(|m| m.as_str())
+}
+
+fn post_to_entry(feed_id: &str, post: &Post) -> Entry {
+    let content = post.content.as_ref().map(|v| {
+        ContentBuilder::default()
+            .value(v.clone())
+            .content_type(Some("html".to_string()))
             .build()
+    });
+    let published = parse_datetime(&post.published);
+    let mut links = vec![
+        LinkBuilder::default()
+            .href(post.url.clone())
+            .rel("alternate")
+            .build(),
+    ];
+    links.extend(post.images.first().map(|i| image_enclosure(&i.url, None)));
+
+    let mut entry = EntryBuilder::default()
+        .title(post.title.clone())
+        .id(format!("{}/{}", feed_id, post.id))
+        .published(published)
+        .author(Person {
+            name: post.author.display_name.clone(),
+            email: None,
+            uri: post.author.url.clone(),
+        })
+        .categories(
+            post.labels
+                .iter()
+                .map(|l| CategoryBuilder::default().term(l.clone()).build())
+                .collect::<Vec<_>>(),
+        )
+        .content(content)
+        .links(links)
+        .build();
+    if let Some(updated) = post.updated.as_deref().and_then(parse_datetime).or(published) {
+        entry.set_updated(updated);
     }
+    entry
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -174,6 +273,11 @@ struct ItemSummary {
 #[serde(rename_all = "camelCase")]
 struct Author {
     display_name: String,
+    url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Image {
     url: String,
 }
 
@@ -185,11 +289,54 @@ struct Post {
     content: Option<String>,
     author: Author,
     published: String,
+    updated: Option<String>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    images: Vec<Image>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 struct ListPostsResponse {
     next_page_token: Option<String>,
+    #[serde(default)]
     items: Vec<Post>,
 }
+
+#[derive(Deserialize, Debug)]
+struct CommentRef {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BloggerComment {
+    id: String,
+    in_reply_to: Option<CommentRef>,
+    published: String,
+    content: String,
+    author: Author,
+}
+
+impl BloggerComment {
+    fn to_comment(&self) -> Comment {
+        Comment {
+            id: self.id.clone(),
+            parent: self.in_reply_to.as_ref().map(|r| r.id.clone()),
+            author: self.author.display_name.clone(),
+            published: parse_datetime(&self.published),
+            author_url: self.author.url.clone(),
+            link: None,
+            content: self.content.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct ListCommentsResponse {
+    next_page_token: Option<String>,
+    #[serde(default)]
+    items: Vec<BloggerComment>,
+}
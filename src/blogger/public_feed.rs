@@ -0,0 +1,204 @@
+use anyhow::Result;
+use atom_syndication::Entry;
+use reqwest::Url;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+
+use crate::common::*;
+use super::{post_id_from_tag, post_to_entry, Author, Image, Post};
+
+// The public feed refuses requests for more than this many results at once.
+static MAX_RESULTS: usize = 150;
+
+// Scrapes a public Blogger blog through its GData JSON feeds, which don't require an API key.
+pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str) -> Result<Box<dyn Blog + 'a>> {
+    let site_url = Url::parse(url)?;
+    let posts_feed_url = site_url.join("/feeds/posts/default")?;
+    let pages_feed_url = site_url.join("/feeds/pages/default")?;
+
+    // Fetch an empty page of results just to read the blog's metadata.
+    let feed_json = retry_request(config, || query_once(client, &posts_feed_url, 1, 0))?.feed;
+    if !feed_json.id.t.starts_with("tag:blogger.com,") {
+        anyhow::bail!("{url} does not serve a Blogger feed");
+    }
+
+    let key = sanitize_blog_key(&feed_json.title.t);
+    let feed_id = format!("{}/{}", config.feed_url_base, key);
+
+    Ok(Box::new(PublicFeedBlog {
+        feed_json,
+        posts_feed_url,
+        pages_feed_url,
+        key,
+        feed_id,
+        config,
+        client,
+    }))
+}
+
+fn query_once(client: &Client, feed_url: &Url, start_index: usize, max_results: usize)
+    -> Result<FeedResponse>
+{
+    let resp = client
+        .get(feed_url.clone())
+        .query(&[
+            ("alt", "json"),
+            ("orderby", "published"),
+            ("start-index", &format!("{start_index}")),
+            ("max-results", &format!("{max_results}")),
+        ])
+        .send()?;
+
+    Ok(resp.error_for_status()?.json()?)
+}
+
+struct PublicFeedBlog<'a> {
+    feed_json: FeedJson,
+    posts_feed_url: Url,
+    pages_feed_url: Url,
+    key: String,
+    feed_id: String,
+    config: &'a Config,
+    client: &'a Client,
+}
+
+impl Blog for PublicFeedBlog<'_> {
+    fn blog_type(&self) -> BlogType {
+        BlogType::Blogger
+    }
+
+    fn feed_data(&self) -> FeedData {
+        FeedData {
+            id: self.feed_id.clone(),
+            key: self.key.clone(),
+            title: self.feed_json.title.t.clone(),
+            url: alternate_link(&self.feed_json.link).unwrap_or_default(),
+        }
+    }
+
+    fn entries(&self) -> Result<Vec<Entry>> {
+        let mut posts: Vec<Entry> = Vec::new();
+
+        for (url, display) in &[(&self.posts_feed_url, "posts"), (&self.pages_feed_url, "pages")] {
+            let mut start_index = 1;
+            let mut pb: Option<indicatif::ProgressBar> = None;
+            loop {
+                let resp = retry_request(self.config, || {
+                    query_once(self.client, url, start_index, MAX_RESULTS)
+                })?;
+                let total: usize = resp.feed.total_results.t.parse()?;
+                if start_index == 1 {
+                    println!(r#"Scraping "{}" ({} {})"#, &self.feed_json.title.t, total, display);
+                    pb = Some(init_progress_bar(total.try_into().unwrap()));
+                }
+                let items = resp.feed.entry;
+                if let Some(pb) = pb.as_ref() { pb.inc(items.len().try_into().unwrap()) };
+                start_index += items.len();
+                posts.extend(
+                    items
+                        .iter()
+                        .filter_map(entry_to_post)
+                        .map(|p| post_to_entry(&self.feed_id, &p)),
+                );
+                if items.is_empty() || start_index > total {
+                    break;
+                }
+
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+            if let Some(pb) = pb { pb.finish() };
+        }
+
+        Ok(posts)
+    }
+}
+
+fn alternate_link(links: &[Link]) -> Option<String> {
+    links.iter().find(|l| l.rel == "alternate").map(|l| l.href.clone())
+}
+
+// Converts a feed entry into the same shape the Blogger API returns, so that entry IDs stay the
+// same whichever way the blog was scraped.
+fn entry_to_post(entry: &FeedEntry) -> Option<Post> {
+    let author = entry.author.first();
+
+    Some(Post {
+        id: post_id_from_tag(&entry.id.t)?.to_string(),
+        url: alternate_link(&entry.link).unwrap_or_default(),
+        title: entry.title.t.clone(),
+        content: entry.content.as_ref().or(entry.summary.as_ref()).map(|c| c.t.clone()),
+        author: Author {
+            display_name: author.map(|a| a.name.t.clone()).unwrap_or_default(),
+            url: author.and_then(|a| a.uri.as_ref()).map(|u| u.t.clone()),
+        },
+        published: entry.published.t.clone(),
+        updated: entry.updated.as_ref().map(|u| u.t.clone()),
+        labels: entry.category.iter().map(|c| c.term.clone()).collect(),
+        images: entry.thumbnail.iter().map(|t| Image { url: t.url.clone() }).collect(),
+    })
+}
+
+// GData wraps every text value in an object like {"$t": "..."}.
+#[derive(Deserialize, Debug)]
+struct Text {
+    #[serde(rename = "$t")]
+    t: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Link {
+    rel: String,
+    href: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Category {
+    term: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Thumbnail {
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedAuthor {
+    name: Text,
+    uri: Option<Text>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedEntry {
+    id: Text,
+    published: Text,
+    updated: Option<Text>,
+    title: Text,
+    content: Option<Text>,
+    summary: Option<Text>,
+    #[serde(default)]
+    link: Vec<Link>,
+    #[serde(default)]
+    author: Vec<FeedAuthor>,
+    #[serde(default)]
+    category: Vec<Category>,
+    // Only the first image of each post is exposed, as a small thumbnail.
+    #[serde(rename = "media$thumbnail")]
+    thumbnail: Option<Thumbnail>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedJson {
+    id: Text,
+    title: Text,
+    #[serde(default)]
+    link: Vec<Link>,
+    #[serde(rename = "openSearch$totalResults")]
+    total_results: Text,
+    #[serde(default)]
+    entry: Vec<FeedEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedResponse {
+    feed: FeedJson,
+}
@@ -1,5 +1,6 @@
 use std::path::{Path, PathBuf};
 
+use atom_syndication::{Entry, Link, LinkBuilder};
 use chrono::{DateTime, FixedOffset, NaiveDateTime, Offset, Utc};
 use convert_case::{Case, Casing};
 use lazy_static::lazy_static;
@@ -7,11 +8,19 @@ use regex::Regex;
 
 mod atom;
 mod blog;
+mod comment;
 mod config;
+mod html;
+mod schedule;
+#[cfg(test)]
+pub mod test_server;
 
-pub use atom::{read_or_create_feed, FeedData};
+pub use atom::{entry_key, read_or_create_feed, synthetic_id, FeedData};
 pub use blog::*;
-pub use config::Config;
+pub use comment::{comment_entry, comments_html, Comment};
+pub use config::{CommentMode, Config};
+pub use html::{escape_html, html_title, link_href, meta_content};
+pub use schedule::{Release, Schedule};
 
 pub fn parse_datetime(s: &str) -> Option<DateTime<FixedOffset>> {
     DateTime::<FixedOffset>::parse_from_rfc3339(s)
@@ -19,12 +28,45 @@ pub fn parse_datetime(s: &str) -> Option<DateTime<FixedOffset>> {
         .map(|d| d.with_timezone(&Utc.fix()))
 }
 
+pub fn parse_rfc2822(s: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::<FixedOffset>::parse_from_rfc2822(s)
+        .ok()
+        .map(|d| d.with_timezone(&Utc.fix()))
+}
+
 pub fn parse_assuming_utc(s: &str) -> Option<DateTime<FixedOffset>> {
     NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S")
         .ok()
         .map(|d| DateTime::<Utc>::from_utc(d, Utc).with_timezone(&Utc.fix()))
 }
 
+// Recovers the source's own post ID from an entry ID of the form "<feed_id>/<post_id>".
+pub fn post_id_from_entry<'e>(feed_id: &str, entry: &'e Entry) -> Option<&'e str> {
+    entry.id.strip_prefix(feed_id)?.strip_prefix('/')
+}
+
+// An enclosure link for a post's featured image. Sources rarely give the media type, in which case
+// it's guessed from the file extension.
+pub fn image_enclosure(url: &str, mime_type: Option<&str>) -> Link {
+    let guessed = || {
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let ext = path.rsplit_once('.')?.1.to_lowercase();
+        Some(match ext.as_str() {
+            "jpg" | "jpeg" => "image/jpeg",
+            "png" => "image/png",
+            "gif" => "image/gif",
+            "webp" => "image/webp",
+            "svg" => "image/svg+xml",
+            _ => return None,
+        })
+    };
+    LinkBuilder::default()
+        .href(url)
+        .rel("enclosure")
+        .mime_type(mime_type.or_else(guessed).map(str::to_string))
+        .build()
+}
+
 pub fn path_from_feed_data(config: &Config, f: &FeedData) -> PathBuf {
     Path::new(&config.feed_path)
         .join(&f.key)
@@ -40,6 +82,21 @@ pub fn sanitize_blog_key(s: &str) -> String {
         .into_owned()
 }
 
+// A made-up ID for entries whose source gives them none, derived from their content so that it
+// stays the same across scrapes. Uses FNV-1a, which unlike std's hashers is fixed forever.
+pub fn stable_id(parts: &[&str]) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        // A separator keeps ("ab", "c") and ("a", "bc") apart.
+        for byte in part.bytes().chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("urn:blog-replay:{hash:016x}")
+}
+
+#[allow(clippy::unnecessary_map_or)]
 pub fn retry_request<F, R>(config: &Config, mut action: F) -> anyhow::Result<R>
 where
     F: FnMut() -> anyhow::Result<R>
@@ -3,9 +3,12 @@ use std::fs::File;
 use std::io::{BufReader, ErrorKind};
 use std::path::Path;
 
-use atom_syndication::{Feed, FeedBuilder, Generator, LinkBuilder};
+use atom_syndication::{Entry, Feed, FeedBuilder, Generator, LinkBuilder};
+use chrono::{SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::stable_id;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct FeedData {
     pub id: String,
@@ -14,6 +17,25 @@ pub struct FeedData {
     pub url: String,
 }
 
+// The key an entry is stored under in its blog's entries tree. Keys sort by publication time,
+// normalized to UTC with a fixed width, and the entry's ID keeps posts published at the same
+// moment apart.
+pub fn entry_key(entry: &Entry) -> String {
+    let time = entry.published.unwrap_or(entry.updated).with_timezone(&Utc);
+    let time = time.to_rfc3339_opts(SecondsFormat::Nanos, true);
+    match entry.id.as_str() {
+        "" => format!("{time}|{}", synthetic_id(entry)),
+        id => format!("{time}|{id}"),
+    }
+}
+
+// An ID for entries that came without one, derived from what they contain.
+pub fn synthetic_id(entry: &Entry) -> String {
+    let content = entry.content.as_ref().and_then(|c| c.value.as_deref());
+    let link = entry.links.first().map(|l| l.href.as_str());
+    stable_id(&[&entry.title.value, content.unwrap_or_default(), link.unwrap_or_default()])
+}
+
 pub fn read_or_create_feed<P: AsRef<Path>>(
     path: P,
     gen: &Generator,
@@ -45,3 +67,39 @@ fn feed_from_metadata(gen: &Generator, feed_data: FeedData) -> Feed {
         .generator(gen.clone())
         .build()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atom_syndication::EntryBuilder;
+    use chrono::DateTime;
+
+    fn entry(id: &str, published: &str) -> Entry {
+        EntryBuilder::default()
+            .id(id)
+            .published(DateTime::parse_from_rfc3339(published).ok())
+            .build()
+    }
+
+    #[test]
+    fn entry_keys_are_unique_and_time_ordered() {
+        let a = entry_key(&entry("blog/1", "2010-01-01T10:00:00+02:00"));
+        let b = entry_key(&entry("blog/2", "2010-01-01T10:00:00+02:00"));
+        let earlier = entry_key(&entry("blog/3", "2010-01-01T07:59:59.5+00:00"));
+        let later = entry_key(&entry("blog/0", "2010-01-01T09:00:00+00:00"));
+        assert_ne!(a, b);
+        assert!(earlier < a && earlier < b);
+        assert!(a < later && b < later);
+    }
+
+    #[test]
+    fn entries_without_ids_get_distinct_keys() {
+        let mut first = entry("", "2010-01-01T10:00:00+00:00");
+        first.set_title("One");
+        let mut second = entry("", "2010-01-01T10:00:00+00:00");
+        second.set_title("Two");
+        assert_ne!(entry_key(&first), entry_key(&second));
+        assert_eq!(entry_key(&first), entry_key(&first.clone()));
+        assert!(entry_key(&first).starts_with("2010-01-01T10:00:00.000000000Z|urn:blog-replay:"));
+    }
+}
@@ -1,14 +1,68 @@
+use std::str::FromStr;
+
 use anyhow::Result;
 use atom_syndication::Entry;
 use reqwest::blocking::Client;
 
 use super::atom::FeedData;
+use super::comment::Comment;
 use super::config::Config;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum BlogType {
     Blogger,
     Wordpress,
+    Ghost,
+    Feed,
+    Wayback,
+    Markdown,
+    Substack,
+    Tumblr,
+}
+
+impl FromStr for BlogType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "blogger" => Ok(BlogType::Blogger),
+            "wordpress" => Ok(BlogType::Wordpress),
+            "ghost" => Ok(BlogType::Ghost),
+            "feed" => Ok(BlogType::Feed),
+            "wayback" => Ok(BlogType::Wayback),
+            "markdown" => Ok(BlogType::Markdown),
+            "substack" => Ok(BlogType::Substack),
+            "tumblr" => Ok(BlogType::Tumblr),
+            _ => anyhow::bail!("Unknown blog type \"{s}\""),
+        }
+    }
+}
+
+type GetBlogFn = for<'a> fn(&'a Config, &'a Client, &str) -> Result<Box<dyn Blog + 'a>>;
+
+// Backends tried when no blog type is given, in order. The Wayback Machine is left out because it
+// is slow and would match almost any URL.
+static DETECTION_ORDER: &[BlogType] = &[
+    BlogType::Blogger,
+    BlogType::Wordpress,
+    BlogType::Ghost,
+    BlogType::Substack,
+    BlogType::Tumblr,
+    BlogType::Feed,
+];
+
+fn scraper(blog_type: BlogType) -> Option<GetBlogFn> {
+    match blog_type {
+        BlogType::Blogger => Some(crate::blogger::get_blog),
+        BlogType::Wordpress => Some(crate::wordpress::get_blog),
+        BlogType::Ghost => Some(crate::ghost::get_blog),
+        BlogType::Feed => Some(crate::feed::get_blog),
+        BlogType::Wayback => Some(crate::wayback::get_blog),
+        BlogType::Substack => Some(crate::substack::get_blog),
+        BlogType::Tumblr => Some(crate::tumblr::get_blog),
+        // Only available through the import-dir command.
+        BlogType::Markdown => None,
+    }
 }
 
 pub trait Blog {
@@ -17,10 +71,41 @@ pub trait Blog {
     fn feed_data(&self) -> FeedData;
 
     fn entries(&self) -> Result<Vec<Entry>>;
+
+    // The number of entries the source claims to have, if it says. Used to detect partial scrapes.
+    fn expected_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn supports_comments(&self) -> bool {
+        false
+    }
+
+    // The comments on one of the entries returned by entries(), in any order.
+    fn comments(&self, _entry: &Entry) -> Result<Vec<Comment>> {
+        Ok(Vec::new())
+    }
 }
 
-pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str) -> Result<Box<dyn Blog + 'a>> {
-    crate::blogger::get_blog(config, client, url)
-        .or_else(|_| crate::wordpress::get_blog(config, client, url))
-        .or_else(|_| anyhow::bail!("Could not determine blog type"))
+pub fn get_blog<'a>(
+    config: &'a Config,
+    client: &'a Client,
+    url: &str,
+    blog_type: Option<BlogType>,
+) -> Result<Box<dyn Blog + 'a>> {
+    if let Some(blog_type) = blog_type {
+        let get_blog = scraper(blog_type)
+            .ok_or_else(|| anyhow::anyhow!("{blog_type:?} blogs can't be scraped from a URL"))?;
+        return get_blog(config, client, url);
+    }
+
+    let mut failures = Vec::new();
+    for &blog_type in DETECTION_ORDER {
+        match scraper(blog_type).map(|get_blog| get_blog(config, client, url)) {
+            Some(Ok(blog)) => return Ok(blog),
+            Some(Err(e)) => failures.push(format!("  {blog_type:?}: {e}")),
+            None => (),
+        }
+    }
+    anyhow::bail!("Could not determine blog type. Tried:\n{}", failures.join("\n"))
 }
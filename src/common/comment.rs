@@ -0,0 +1,123 @@
+use std::collections::HashSet;
+
+use atom_syndication::{ContentBuilder, Entry, EntryBuilder, LinkBuilder, Person};
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+
+use super::html::escape_html;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Comment {
+    pub id: String,
+    // The ID of the comment this one replies to, if any.
+    pub parent: Option<String>,
+    pub author: String,
+    pub published: Option<DateTime<FixedOffset>>,
+    pub author_url: Option<String>,
+    // Where the comment can be read on the blog itself.
+    pub link: Option<String>,
+    // HTML, as rendered by the blog.
+    pub content: String,
+}
+
+// Renders a post's comments as nested lists, with each reply under the comment it answers.
+pub fn comments_html(comments: &[Comment]) -> String {
+    let ids: HashSet<&str> = comments.iter().map(|c| c.id.as_str()).collect();
+    // Replies to comments we don't have are shown at the top level rather than dropped.
+    let roots: Vec<&Comment> = comments
+        .iter()
+        .filter(|c| !c.parent.as_deref().is_some_and(|p| ids.contains(p)))
+        .collect();
+
+    let mut html = String::from("<hr/><h3>Comments</h3>");
+    push_thread(&mut html, comments, &roots);
+    html
+}
+
+fn push_thread(html: &mut String, comments: &[Comment], thread: &[&Comment]) {
+    html.push_str("<ul>");
+    for comment in thread {
+        // Commenters pick their own URLs, so only link to web pages.
+        let author = match &comment.author_url {
+            Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+                format!("<a href=\"{}\">{}</a>", escape_html(url), escape_html(&comment.author))
+            }
+            _ => escape_html(&comment.author),
+        };
+        let date = comment
+            .published
+            .map(|d| format!(" on {}", d.format("%B %-d, %Y")))
+            .unwrap_or_default();
+        html.push_str(&format!("<li><p><strong>{author}</strong>{date}:</p>{}", comment.content));
+        let replies: Vec<&Comment> = comments
+            .iter()
+            .filter(|c| c.parent.as_deref() == Some(comment.id.as_str()))
+            .collect();
+        if !replies.is_empty() {
+            push_thread(html, comments, &replies);
+        }
+        html.push_str("</li>");
+    }
+    html.push_str("</ul>");
+}
+
+// Turns a comment into an entry of the companion comments feed for the given post.
+pub fn comment_entry(post: &Entry, comment: &Comment) -> Entry {
+    let content = ContentBuilder::default()
+        .value(comment.content.clone())
+        .content_type(Some("html".to_string()))
+        .build();
+    let link = comment.link.clone().or_else(|| {
+        post.links.iter().find(|l| l.rel == "alternate").map(|l| l.href.clone())
+    });
+
+    EntryBuilder::default()
+        .title(format!("{} on \"{}\"", comment.author, post.title.value))
+        .id(format!("{}/comments/{}", post.id, comment.id))
+        .published(comment.published)
+        .author(Person {
+            name: comment.author.clone(),
+            email: None,
+            uri: comment.author_url.clone(),
+        })
+        .content(content)
+        .links(
+            link.map(|href| LinkBuilder::default().href(href).rel("alternate").build())
+                .into_iter()
+                .collect::<Vec<_>>(),
+        )
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn comment(author_url: &str) -> Comment {
+        Comment {
+            id: "1".to_string(),
+            parent: None,
+            author: "Eve \"<b>\"".to_string(),
+            published: None,
+            author_url: Some(author_url.to_string()),
+            link: None,
+            content: "<p>Hi</p>".to_string(),
+        }
+    }
+
+    #[test]
+    fn escapes_author_url() {
+        let html = comments_html(&[comment("https://e.example/\" onmouseover=\"alert(1)")]);
+        assert!(html.contains(
+            "<a href=\"https://e.example/&quot; onmouseover=&quot;alert(1)\">\
+            Eve &quot;&lt;b&gt;&quot;</a>"
+        ), "{html}");
+    }
+
+    #[test]
+    fn only_links_web_urls() {
+        let html = comments_html(&[comment("javascript:alert(1)")]);
+        assert!(!html.contains("<a "), "{html}");
+        assert!(!html.contains("javascript"), "{html}");
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::default::Default;
 
 use serde::{Deserialize, Serialize};
@@ -9,6 +10,29 @@ pub struct Config {
     pub feed_path: String,
     pub max_retries: usize,
     pub max_entries: Option<usize>,
+    #[serde(default = "default_wayback_url")]
+    pub wayback_url: String,
+    #[serde(default)]
+    pub tumblr_api_key: String,
+    #[serde(default)]
+    pub comment_mode: CommentMode,
+    #[serde(default)]
+    pub ghost_content_api_keys: HashMap<String, String>,
+}
+
+// How scraped comments are replayed along with their posts.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentMode {
+    // Appended to the end of each post's content.
+    #[default]
+    Inline,
+    // Published as entries of a separate "<key>-comments" feed.
+    Feed,
+}
+
+fn default_wayback_url() -> String {
+    "https://web.archive.org".to_string()
 }
 
 impl Default for Config {
@@ -19,6 +43,10 @@ impl Default for Config {
             feed_path: "".to_string(),
             max_retries: 5,
             max_entries: None,
+            wayback_url: default_wayback_url(),
+            tumblr_api_key: "".to_string(),
+            comment_mode: CommentMode::default(),
+            ghost_content_api_keys: HashMap::new(),
         }
     }
 }
@@ -0,0 +1,53 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+// Escapes text for use in element content or a double-quoted attribute.
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+// Finds the value of a <meta property="..."> or <meta name="..."> tag.
+pub fn meta_content(html: &str, property: &str) -> Option<String> {
+    lazy_static! {
+        static ref META_TAG: Regex = Regex::new(r"(?is)<meta\b[^>]*>").unwrap();
+        static ref CONTENT: Regex = Regex::new(r#"(?i)content\s*=\s*["']([^"']*)["']"#).unwrap();
+    };
+    let name = Regex::new(&format!(
+        r#"(?i)(property|name)\s*=\s*["']{}["']"#,
+        regex::escape(property)
+    ))
+    .ok()?;
+    META_TAG
+        .find_iter(html)
+        .map(|m| m.as_str())
+        .find(|tag| name.is_match(tag))
+        .and_then(|tag| CONTENT.captures(tag))
+        .map(|c| c[1].to_string())
+}
+
+pub fn html_title(html: &str) -> Option<String> {
+    lazy_static! {
+        static ref TITLE: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+    };
+    meta_content(html, "og:title")
+        .or_else(|| TITLE.captures(html).map(|c| c[1].trim().to_string()))
+}
+
+// Finds the href of a <link rel="..."> tag.
+pub fn link_href(html: &str, rel: &str) -> Option<String> {
+    lazy_static! {
+        static ref LINK_TAG: Regex = Regex::new(r"(?is)<link\b[^>]*>").unwrap();
+        static ref HREF: Regex = Regex::new(r#"(?i)href\s*=\s*["']([^"']+)["']"#).unwrap();
+    };
+    let rel = Regex::new(&format!(r#"(?i)rel\s*=\s*["']{}["']"#, regex::escape(rel))).ok()?;
+    LINK_TAG
+        .find_iter(html)
+        .map(|m| m.as_str())
+        .find(|tag| rel.is_match(tag))
+        .and_then(|tag| HREF.captures(tag))
+        .map(|c| c[1].to_string())
+}
@@ -0,0 +1,451 @@
+use std::fmt;
+
+use anyhow::Result;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, Offset, TimeZone, Utc, Weekday,
+};
+use serde::{Deserialize, Serialize};
+
+// How often a blog releases entries. Blogs without a schedule release one entry per generate run.
+// Stored with bincode, so new variants must only ever be added at the end.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Schedule {
+    // Releases `count` entries every `interval` seconds.
+    Every { count: usize, interval: i64 },
+    // Releases `count` entries once a week, on the given day in local time.
+    Weekly { count: usize, weekday: Weekday },
+    // Keeps the original gaps between entries, sped up `factor` times. The entry published at
+    // `origin` was due at `start`.
+    Scaled { factor: f64, start: DateTime<Utc>, origin: DateTime<FixedOffset> },
+    // Releases each entry on the same calendar date it was published, `years` later. Entries
+    // whose anniversary had already passed at `start` are skipped.
+    Anniversary { years: i32, start: DateTime<Utc> },
+}
+
+// Which entries a schedule says are due.
+pub enum Release {
+    // The given number of the oldest remaining entries.
+    Count(usize),
+    // Every remaining entry published up to the given time.
+    PublishedBy(DateTime<FixedOffset>),
+    // Every remaining entry published after the first time and up to the second. Earlier ones
+    // are skipped.
+    PublishedBetween(DateTime<FixedOffset>, DateTime<FixedOffset>),
+}
+
+static UNITS: &[(&str, i64)] = &[
+    ("week", 7 * 24 * 60 * 60),
+    ("day", 24 * 60 * 60),
+    ("hour", 60 * 60),
+    ("minute", 60),
+];
+
+// Words that can be left out of a spec, as in "3 posts every Monday".
+static FILLER: &[&str] = &["post", "posts", "entry", "entries", "on"];
+
+fn parse_count(s: &str) -> Option<usize> {
+    let words = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"];
+    match s {
+        "a" | "an" => Some(1),
+        _ => s.parse().ok().or_else(|| words.iter().position(|w| *w == s).map(|i| i + 1)),
+    }
+}
+
+fn parse_unit(s: &str) -> Option<i64> {
+    let s = s.strip_suffix('s').unwrap_or(s);
+    let s = match s {
+        "min" => "minute",
+        "hr" => "hour",
+        _ => s,
+    };
+    UNITS.iter().find(|(name, _)| *name == s).map(|(_, secs)| *secs)
+}
+
+fn parse_weekday(s: &str) -> Option<Weekday> {
+    s.parse().ok().or_else(|| s.strip_suffix('s')?.parse().ok())
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+// Parses speedups such as "10x", "10x faster" or "10 times faster".
+fn parse_factor(words: &[&str]) -> Option<f64> {
+    let (factor, rest) = match words {
+        [n, rest @ ..] if n.ends_with('x') => (n.trim_end_matches('x').parse().ok()?, rest),
+        [n, "times", rest @ ..] => (n.parse().ok()?, rest),
+        _ => return None,
+    };
+    matches!(rest, [] | ["faster"] | ["speed"]).then_some(factor)
+}
+
+// Parses delays such as "10 years late", "10 years ago" or "anniversary 10".
+fn parse_years(words: &[&str]) -> Option<i32> {
+    match words {
+        [n, "year" | "years", "late" | "later" | "ago"] | ["anniversary", n] => {
+            parse_count(n).and_then(|n| n.try_into().ok())
+        }
+        _ => None,
+    }
+}
+
+// The last original date whose anniversary, `years` later, is no later than `today`. Posts from
+// a leap day come out on Feb 28 in years without one.
+fn anniversary_cutoff(today: NaiveDate, years: i32) -> Option<NaiveDate> {
+    let year = today.year().checked_sub(years)?;
+    let is_leap = |y| NaiveDate::from_ymd_opt(y, 2, 29).is_some();
+    match (today.month(), today.day()) {
+        (2, 28) if is_leap(year) && !is_leap(today.year()) => NaiveDate::from_ymd_opt(year, 2, 29),
+        (2, 29) if !is_leap(year) => NaiveDate::from_ymd_opt(year, 2, 28),
+        (month, day) => NaiveDate::from_ymd_opt(year, month, day),
+    }
+}
+
+// The end, in local time, of the last original date due on `day`.
+fn anniversary_end(day: NaiveDate, years: i32) -> Option<DateTime<FixedOffset>> {
+    let next_day = anniversary_cutoff(day, years)?.succ_opt()?;
+    let end = Local.from_local_datetime(&next_day.and_hms(0, 0, 0)).earliest()?;
+    Some((end - Duration::nanoseconds(1)).with_timezone(&Utc.fix()))
+}
+
+impl Schedule {
+    // Accepts specs such as "one per day", "every 12 hours", "3 posts every Monday", "daily" or
+    // "10x" or "10 years late". Scaled schedules start now, from the next entry to be replayed,
+    // and anniversary schedules start with the entries due today.
+    pub fn parse(s: &str, now: DateTime<Utc>, next_published: Option<DateTime<FixedOffset>>)
+        -> Result<Self>
+    {
+        let lower = s.to_lowercase();
+        let mut words: Vec<&str> = lower
+            .split_whitespace()
+            .filter(|w| !FILLER.contains(w))
+            .collect();
+        let shorthand = match words.as_slice() {
+            ["hourly"] => Some("hour"),
+            ["daily"] => Some("day"),
+            ["weekly"] => Some("week"),
+            _ => None,
+        };
+        if let Some(unit) = shorthand {
+            words = vec!["every", unit];
+        }
+        if let Some(factor) = parse_factor(&words) {
+            if factor <= 0.0 || !factor.is_finite() {
+                anyhow::bail!("The speedup must be a positive number");
+            }
+            let origin = next_published
+                .ok_or_else(|| anyhow::anyhow!("There are no entries left to replay"))?;
+            return Ok(Schedule::Scaled { factor, start: now, origin });
+        }
+        if let Some(years) = parse_years(&words) {
+            if years <= 0 {
+                anyhow::bail!("The delay must be a positive number of years");
+            }
+            return Ok(Schedule::Anniversary { years, start: now });
+        }
+
+        let (count, rest) = match words.split_first() {
+            Some((first, rest)) if parse_count(first).is_some() && !rest.is_empty() => {
+                (parse_count(first).unwrap_or(1), rest)
+            }
+            _ => (1, words.as_slice()),
+        };
+        let schedule = match rest {
+            ["per" | "a" | "an" | "each" | "every", day] if parse_weekday(day).is_some() => {
+                parse_weekday(day).map(|weekday| Schedule::Weekly { count, weekday })
+            }
+            ["per" | "a" | "an" | "each" | "every", unit] => {
+                parse_unit(unit).map(|interval| Schedule::Every { count, interval })
+            }
+            // Intervals too long to represent are rejected like any other bad spec.
+            ["every", n, unit] => parse_count(n)
+                .and_then(|n| i64::try_from(n).ok())
+                .zip(parse_unit(unit))
+                .and_then(|(n, secs)| n.checked_mul(secs))
+                .map(|interval| Schedule::Every { count, interval }),
+            _ => None,
+        };
+        match schedule {
+            Some(s) if count > 0 && !matches!(s, Schedule::Every { interval: 0, .. }) => Ok(s),
+            _ => anyhow::bail!(
+                "Can't understand schedule \"{s}\"; try something like \"one per day\", \
+                \"every 12 hours\", \"3 posts every Monday\", \"10x\" \
+                or \"10 years late\""
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Schedule::Every { count, interval } => {
+                let (unit, secs) = UNITS
+                    .iter()
+                    .find(|(_, secs)| interval % secs == 0)
+                    .unwrap_or(&("second", 1));
+                match interval / secs {
+                    1 => write!(f, "{count} every {unit}"),
+                    n => write!(f, "{count} every {n} {unit}s"),
+                }
+            }
+            Schedule::Weekly { count, weekday } => {
+                write!(f, "{count} every {}", weekday_name(*weekday))
+            }
+            Schedule::Scaled { factor, start, .. } => {
+                write!(f, "{factor}x real time since {}", start.with_timezone(&Local).format("%F %R"))
+            }
+            Schedule::Anniversary { years, start } => {
+                let since = start.with_timezone(&Local).format("%F");
+                match years {
+                    1 => write!(f, "1 year late since {since}"),
+                    _ => write!(f, "{years} years late since {since}"),
+                }
+            }
+        }
+    }
+}
+
+impl Schedule {
+    // Returns which entries are due now, and the release time to record for them. Recorded times
+    // stay on the schedule's own grid, so that late generate runs don't make it drift. Entries
+    // that came due during missed runs are all released by the next one.
+    pub fn due(&self, last_release: Option<DateTime<Utc>>, now: DateTime<Utc>)
+        -> Option<(Release, DateTime<Utc>)>
+    {
+        let catch_up = |count: usize, periods: i64| {
+            Release::Count(count.saturating_mul(periods.try_into().unwrap_or(usize::MAX)))
+        };
+        match *self {
+            Schedule::Every { count, interval } => match last_release {
+                None => Some((Release::Count(count), now)),
+                Some(last) => {
+                    let periods = (now - last).num_seconds() / interval;
+                    (periods > 0).then(|| {
+                        (catch_up(count, periods), last + Duration::seconds(periods * interval))
+                    })
+                }
+            },
+            Schedule::Weekly { count, weekday } => {
+                let today = now.with_timezone(&Local).naive_local().date();
+                let days_back = (7 + today.weekday().num_days_from_monday()
+                    - weekday.num_days_from_monday()) % 7;
+                let release_day = today - Duration::days(days_back.into());
+                let release_start = Local
+                    .from_local_datetime(&release_day.and_hms(0, 0, 0))
+                    .earliest()?
+                    .with_timezone(&Utc);
+                // A new schedule waits for its first release day.
+                let periods = match last_release {
+                    None => i64::from(days_back == 0),
+                    Some(last) if last < release_start => (release_start - last).num_weeks() + 1,
+                    Some(_) => 0,
+                };
+                (periods > 0).then(|| (catch_up(count, periods), now))
+            }
+            Schedule::Scaled { .. } => Some((Release::PublishedBy(self.reached(now)?), now)),
+            Schedule::Anniversary { years, start } => {
+                // Entries due before the schedule was set would otherwise all come at once.
+                let start_day = start.with_timezone(&Local).naive_local().date();
+                let skipped = anniversary_end(start_day.pred_opt()?, years)?;
+                Some((Release::PublishedBetween(skipped, self.reached(now)?), now))
+            }
+        }
+    }
+
+    // For scaled and anniversary schedules, the original date that the replay has caught up to.
+    pub fn reached(&self, now: DateTime<Utc>) -> Option<DateTime<FixedOffset>> {
+        match *self {
+            Schedule::Scaled { factor, start, origin } => {
+                let elapsed = (now - start).num_milliseconds() as f64 * factor;
+                // Saturate rather than overflow for absurd speedups.
+                let elapsed = Duration::milliseconds(elapsed.min(i64::MAX as f64 / 2.0) as i64);
+                origin.checked_add_signed(elapsed).or(Some(origin))
+            }
+            Schedule::Anniversary { years, .. } => {
+                anniversary_end(now.with_timezone(&Local).naive_local().date(), years)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parses_specs() {
+        let now = utc("2024-05-01T12:00:00Z");
+        let origin = DateTime::parse_from_rfc3339("2010-01-01T00:00:00+00:00").unwrap();
+        let day = 24 * 60 * 60;
+        let cases = [
+            ("one per day", Schedule::Every { count: 1, interval: day }),
+            ("every 12 hours", Schedule::Every { count: 1, interval: 12 * 60 * 60 }),
+            ("3 posts every Monday", Schedule::Weekly { count: 3, weekday: Weekday::Mon }),
+            ("2 a week", Schedule::Every { count: 2, interval: 7 * day }),
+            ("every 30 mins", Schedule::Every { count: 1, interval: 30 * 60 }),
+            ("hourly", Schedule::Every { count: 1, interval: 60 * 60 }),
+            ("daily", Schedule::Every { count: 1, interval: day }),
+            ("Weekly", Schedule::Every { count: 1, interval: 7 * day }),
+            ("10x", Schedule::Scaled { factor: 10.0, start: now, origin }),
+            ("2.5 times faster", Schedule::Scaled { factor: 2.5, start: now, origin }),
+            ("10 years late", Schedule::Anniversary { years: 10, start: now }),
+            ("anniversary 1", Schedule::Anniversary { years: 1, start: now }),
+        ];
+        for (spec, expected) in cases {
+            let parsed = Schedule::parse(spec, now, Some(origin));
+            assert_eq!(parsed.ok(), Some(expected), "{spec}");
+        }
+    }
+
+    #[test]
+    fn rejects_bad_specs() {
+        let now = utc("2024-05-01T12:00:00Z");
+        let origin = DateTime::parse_from_rfc3339("2010-01-01T00:00:00+00:00").unwrap();
+        let specs = [
+            "",
+            "sometimes",
+            "per fortnight",
+            "0 per day",
+            "every 0 hours",
+            "every 9223372036854775807 weeks",
+            "3 posts every Funday",
+            "0x",
+            "-2x",
+            "infx",
+            "0 years late",
+        ];
+        for spec in specs {
+            assert!(Schedule::parse(spec, now, Some(origin)).is_err(), "{spec}");
+        }
+        // A sped-up replay needs an entry to start from.
+        assert!(Schedule::parse("10x", now, None).is_err());
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn anniversary_cutoff_handles_leap_days() {
+        let cases = [
+            // (today, years late, last original date due)
+            ("2025-02-28", 1, "2024-02-29"),
+            ("2025-03-01", 1, "2024-03-01"),
+            ("2024-02-28", 1, "2023-02-28"),
+            ("2024-02-29", 1, "2023-02-28"),
+            ("2024-03-01", 1, "2023-03-01"),
+            ("2024-02-28", 4, "2020-02-28"),
+            ("2024-02-29", 4, "2020-02-29"),
+            ("2024-03-01", 4, "2020-03-01"),
+            ("2023-02-28", 3, "2020-02-29"),
+            ("2023-03-01", 3, "2020-03-01"),
+            ("2025-02-27", 1, "2024-02-27"),
+        ];
+        for (today, years, expected) in cases {
+            assert_eq!(
+                anniversary_cutoff(date(today), years),
+                Some(date(expected)),
+                "{today}, {years} years late"
+            );
+        }
+    }
+
+    // Times in local time, so that tests don't depend on the time zone.
+    fn local(s: &str) -> DateTime<Utc> {
+        Local
+            .from_local_datetime(&chrono::NaiveDateTime::parse_from_str(s, "%F %T").unwrap())
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn published(s: &str) -> DateTime<FixedOffset> {
+        local(s).with_timezone(&Utc.fix())
+    }
+
+    #[test]
+    fn anniversary_catches_up_after_missed_runs() {
+        let schedule = Schedule::Anniversary { years: 10, start: local("2024-05-01 12:00:00") };
+        let now = local("2024-05-10 12:00:00");
+        let last = local("2024-05-01 12:00:00");
+        let Some((Release::PublishedBetween(after, by), released_at)) =
+            schedule.due(Some(last), now)
+        else {
+            panic!("anniversary schedules are always due");
+        };
+        assert_eq!(released_at, now);
+        // Everything up to the end of May 10, 2014 is due, including the days that generate
+        // didn't run.
+        assert!(after < published("2014-05-02 00:00:00"));
+        assert!(by >= published("2014-05-10 23:59:59"));
+        assert!(by < published("2014-05-11 00:00:00"));
+    }
+
+    #[test]
+    fn anniversary_skips_entries_due_before_it_was_set() {
+        let start = local("2024-05-10 12:00:00");
+        let schedule = Schedule::Anniversary { years: 10, start };
+        let Some((Release::PublishedBetween(after, by), _)) = schedule.due(None, start) else {
+            panic!("anniversary schedules are always due");
+        };
+        // Only entries from May 10, 2014 are released when the schedule is set; older ones,
+        // down to 2010, aren't flooded into the feed.
+        assert!(published("2010-01-01 00:00:00") <= after);
+        assert!(published("2014-05-09 23:59:59") <= after);
+        assert!(published("2014-05-10 00:00:00") > after);
+        assert!(published("2014-05-10 23:59:59") <= by);
+
+        // Set on March 1, 2025 a year late, Feb 29, 2024 had its anniversary the day before.
+        let start = local("2025-03-01 09:00:00");
+        let schedule = Schedule::Anniversary { years: 1, start };
+        let Some((Release::PublishedBetween(after, _), _)) = schedule.due(None, start) else {
+            panic!("anniversary schedules are always due");
+        };
+        assert!(published("2024-02-29 12:00:00") <= after);
+        assert!(published("2024-03-01 00:00:00") > after);
+    }
+
+    #[test]
+    fn every_catches_up_on_its_grid_after_missed_runs() {
+        let schedule = Schedule::Every { count: 2, interval: 24 * 60 * 60 };
+        let last = utc("2024-05-01T06:00:00Z");
+        assert!(schedule.due(Some(last), utc("2024-05-02T05:59:59Z")).is_none());
+        let Some((Release::Count(6), released_at)) =
+            schedule.due(Some(last), utc("2024-05-04T09:30:00Z"))
+        else {
+            panic!("three missed days should release three days' worth");
+        };
+        assert_eq!(released_at, utc("2024-05-04T06:00:00Z"));
+        assert!(matches!(schedule.due(None, last), Some((Release::Count(2), t)) if t == last));
+    }
+
+    #[test]
+    fn weekly_catches_up_after_missed_weeks() {
+        let schedule = Schedule::Weekly { count: 3, weekday: Weekday::Wed };
+        let now = local("2024-05-22 12:00:00");
+        assert!(schedule.due(Some(local("2024-05-22 09:00:00")), now).is_none());
+        assert!(matches!(
+            schedule.due(Some(local("2024-05-15 09:00:00")), now),
+            Some((Release::Count(3), _))
+        ));
+        assert!(matches!(
+            schedule.due(Some(local("2024-05-01 09:00:00")), now),
+            Some((Release::Count(9), _))
+        ));
+        assert!(matches!(schedule.due(None, now), Some((Release::Count(3), _))));
+        assert!(schedule.due(None, local("2024-05-21 12:00:00")).is_none());
+    }
+}
@@ -0,0 +1,76 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+
+// A minimal HTTP server for tests, answering each request with whatever `handler` returns for its
+// path and query, or a 404.
+pub struct TestServer {
+    pub url: String,
+    pub requests: Arc<Mutex<Vec<String>>>,
+}
+
+pub struct TestResponse {
+    pub status: u16,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+impl TestResponse {
+    pub fn ok(body: impl Into<String>) -> Self {
+        TestResponse { status: 200, headers: Vec::new(), body: body.into() }
+    }
+
+    pub fn status(status: u16) -> Self {
+        TestResponse { status, headers: Vec::new(), body: String::new() }
+    }
+
+    pub fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+pub fn serve<F>(handler: F) -> TestServer
+where
+    F: Fn(&str) -> Option<String> + Send + 'static,
+{
+    serve_responses(move |target| match handler(target) {
+        Some(body) => TestResponse::ok(body),
+        None => TestResponse::status(404),
+    })
+}
+
+pub fn serve_responses<F>(handler: F) -> TestServer
+where
+    F: Fn(&str) -> TestResponse + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut header = String::new();
+            while reader.read_line(&mut header).is_ok_and(|n| n > 2) {
+                header.clear();
+            }
+            let target = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+            log.lock().unwrap().push(target.clone());
+            let resp = handler(&target);
+            let headers: String =
+                resp.headers.iter().map(|(name, value)| format!("{name}: {value}\r\n")).collect();
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {} Test\r\nContent-Length: {}\r\nConnection: close\r\n{headers}\r\n{}",
+                resp.status,
+                resp.body.len(),
+                resp.body
+            );
+        }
+    });
+    TestServer { url, requests }
+}
@@ -0,0 +1,315 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use anyhow::Result;
+use atom_syndication::Entry;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sled::transaction::{ConflictableTransactionError, Transactional};
+use sled::IVec;
+
+use crate::common::{Comment, FeedData, Schedule};
+
+// Version of the on-disk layout. Bump it whenever a stored type or key format changes. Add a
+// migration step below for changes that need existing data rewritten, or have the changed type's
+// Stored::upgrade read values written under older versions.
+pub const SCHEMA_VERSION: u32 = 2;
+
+static VERSION_KEY: &str = "schema_version";
+
+// Changes a migration step would make, by tree name. A None value removes the key.
+#[derive(Default)]
+struct Plan {
+    writes: BTreeMap<String, Vec<(IVec, Option<IVec>)>>,
+    notes: Vec<String>,
+}
+
+struct Migration {
+    // The schema version this step upgrades to.
+    version: u32,
+    description: &'static str,
+    plan: fn(&sled::Db) -> Result<Plan>,
+}
+
+static MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "key entries by publication time and entry ID",
+        plan: plan_entry_keys,
+    },
+    Migration {
+        version: 2,
+        description: "wrap stored values in versioned envelopes",
+        plan: plan_envelopes,
+    },
+];
+
+// A type stored in the DB, in an envelope that records the schema version it was written under.
+pub trait Stored: Serialize + DeserializeOwned {
+    // Reads a value written under an older schema version. Types whose encoding hasn't changed
+    // since then are read as they are.
+    fn upgrade(version: u32, bytes: &[u8]) -> Result<Self> {
+        bincode::deserialize(bytes)
+            .map_err(|e| anyhow::anyhow!("Can't read value from schema version {version}: {e}"))
+    }
+}
+
+impl Stored for FeedData {}
+impl Stored for Entry {}
+impl Stored for DateTime<Utc> {}
+impl Stored for Vec<Comment> {}
+impl Stored for Schedule {}
+impl Stored for String {}
+
+// The envelope is the version as a bincode u32, followed by the value's own bincode.
+const ENVELOPE_LEN: usize = 4;
+
+pub fn encode<T: Stored>(value: &T) -> Result<Vec<u8>> {
+    Ok(bincode::serialize(&(SCHEMA_VERSION, value))?)
+}
+
+pub fn decode<T: Stored>(bytes: &[u8]) -> Result<T> {
+    if bytes.len() < ENVELOPE_LEN {
+        anyhow::bail!("Stored value is too short to have a schema version");
+    }
+    let (envelope, value) = bytes.split_at(ENVELOPE_LEN);
+    match bincode::deserialize::<u32>(envelope)? {
+        SCHEMA_VERSION => Ok(bincode::deserialize(value)?),
+        version if version < SCHEMA_VERSION => T::upgrade(version, value),
+        version => anyhow::bail!(
+            "Stored value has schema version {version}, which is newer than this version of \
+            blog-replay supports ({SCHEMA_VERSION})"
+        ),
+    }
+}
+
+// Opens the DB, upgrading data written by older versions first.
+pub fn open(db_path: &Path) -> Result<sled::Db> {
+    let db = sled::open(db_path)?;
+    let version = schema_version(&db)?;
+    if version > SCHEMA_VERSION {
+        anyhow::bail!(
+            "The database has schema version {version}, which is newer than this version of \
+            blog-replay supports ({SCHEMA_VERSION})"
+        );
+    }
+    for step in MIGRATIONS.iter().filter(|m| m.version > version) {
+        let plan = (step.plan)(&db)?;
+        for note in &plan.notes {
+            println!("Migrating to schema version {}: {}", step.version, note);
+        }
+        apply(&db, step.version, plan)?;
+    }
+    Ok(db)
+}
+
+// Prints what open() would change, without changing anything. Each step is planned against the
+// DB as it is now, so later steps may describe data that earlier steps would have moved.
+pub fn dry_run(db_path: &Path) -> Result<()> {
+    let db = sled::open(db_path)?;
+    let version = schema_version(&db)?;
+    println!("Schema version {version}, current version is {SCHEMA_VERSION}");
+    for step in MIGRATIONS.iter().filter(|m| m.version > version) {
+        println!("{}: {}", step.version, step.description);
+        let plan = (step.plan)(&db)?;
+        if plan.notes.is_empty() {
+            println!("   no changes");
+        }
+        for note in &plan.notes {
+            println!("   {note}");
+        }
+    }
+    Ok(())
+}
+
+pub fn schema_version(db: &sled::Db) -> Result<u32> {
+    match db.get(VERSION_KEY)? {
+        Some(v) => Ok(bincode::deserialize(&v)?),
+        None => Ok(0),
+    }
+}
+
+// Applies a step's changes and records the new version in a single transaction, so that an
+// interrupted migration is simply run again on the next open.
+fn apply(db: &sled::Db, version: u32, plan: Plan) -> Result<()> {
+    let mut trees: Vec<sled::Tree> = vec![(**db).clone()];
+    for name in plan.writes.keys() {
+        trees.push(db.open_tree(name)?);
+    }
+    let encoded_version = bincode::serialize(&version)?;
+    trees
+        .as_slice()
+        .transaction(|tx| {
+            for (writes, tree) in plan.writes.values().zip(&tx[1..]) {
+                for (key, val) in writes {
+                    match val {
+                        Some(val) => tree.insert(key, val.clone())?,
+                        None => tree.remove(key)?,
+                    };
+                }
+            }
+            tx[0].insert(VERSION_KEY, encoded_version.clone())?;
+            Ok::<_, ConflictableTransactionError<()>>(())
+        })
+        .map_err(|e| anyhow::anyhow!("Migration to schema version {version} failed: {e:?}"))?;
+    db.flush()?;
+    Ok(())
+}
+
+// Opens a tree only if it exists, so that planning a migration doesn't create empty ones.
+fn existing_tree(db: &sled::Db, name: &str) -> Result<Option<sled::Tree>> {
+    if !db.tree_names().iter().any(|n| **n == *name.as_bytes()) {
+        return Ok(None);
+    }
+    Ok(Some(db.open_tree(name)?))
+}
+
+fn blog_keys(db: &sled::Db) -> Result<Vec<String>> {
+    let Some(meta_tree) = existing_tree(db, "feed_metadata")? else { return Ok(Vec::new()) };
+    let mut keys = Vec::new();
+    for item in meta_tree.iter() {
+        let (key, _) = item?;
+        keys.push(String::from_utf8(key.to_vec())?);
+    }
+    Ok(keys)
+}
+
+// The entry keys introduced by schema version 1. A copy of what common::entry_key() was then, so
+// that this step doesn't change along with later key formats.
+fn v1_entry_key(entry: &Entry) -> String {
+    let time = entry.published.unwrap_or(entry.updated).with_timezone(&Utc);
+    format!("{}|{}", time.to_rfc3339_opts(SecondsFormat::Nanos, true), entry.id)
+}
+
+// Older versions keyed entries by publication time alone, so posts published at the same moment
+// overwrote each other. Moves every entry, and its delivery record, to its v1 key.
+fn plan_entry_keys(db: &sled::Db) -> Result<Plan> {
+    let mut plan = Plan::default();
+    for key in blog_keys(db)? {
+        let Some(entry_tree) = existing_tree(db, &format!("entries_{key}"))? else { continue };
+        let delivered_tree = existing_tree(db, &format!("delivered_{key}"))?;
+        let mut entry_writes = Vec::new();
+        let mut delivered_writes = Vec::new();
+        for item in entry_tree.iter() {
+            let (old_key, val) = item?;
+            let entry: Entry = bincode::deserialize(&val)?;
+            let new_key = IVec::from(v1_entry_key(&entry).as_bytes());
+            if old_key == new_key {
+                continue;
+            }
+            let released_at = delivered_tree.as_ref().map(|t| t.get(&old_key)).transpose()?;
+            if let Some(released_at) = released_at.flatten() {
+                delivered_writes.push((old_key.clone(), None));
+                delivered_writes.push((new_key.clone(), Some(released_at)));
+            }
+            entry_writes.push((old_key, None));
+            entry_writes.push((new_key, Some(val)));
+        }
+        if !entry_writes.is_empty() {
+            plan.notes.push(format!("{key}: rekey {} entries", entry_writes.len() / 2));
+            plan.writes.insert(format!("entries_{key}"), entry_writes);
+            if !delivered_writes.is_empty() {
+                plan.writes.insert(format!("delivered_{key}"), delivered_writes);
+            }
+        }
+    }
+    Ok(plan)
+}
+
+// Values used to be bare bincode. The envelope is the version followed by the same bincode, so
+// existing values only need the prefix.
+fn plan_envelopes(db: &sled::Db) -> Result<Plan> {
+    let prefix = bincode::serialize(&2u32)?;
+    debug_assert_eq!(prefix.len(), ENVELOPE_LEN);
+    let mut trees = vec![
+        "feed_metadata".to_string(),
+        "replay_schedules".to_string(),
+        "replay_state".to_string(),
+    ];
+    for key in blog_keys(db)? {
+        for tree in ["entries", "delivered", "comments"] {
+            trees.push(format!("{tree}_{key}"));
+        }
+    }
+    let mut plan = Plan::default();
+    for name in trees {
+        let Some(tree) = existing_tree(db, &name)? else { continue };
+        let mut writes = Vec::new();
+        for item in tree.iter() {
+            let (key, val) = item?;
+            writes.push((key, Some(IVec::from([prefix.as_slice(), &val].concat()))));
+        }
+        if !writes.is_empty() {
+            plan.notes.push(format!("{name}: wrap {} values", writes.len()));
+            plan.writes.insert(name, writes);
+        }
+    }
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atom_syndication::EntryBuilder;
+
+    #[test]
+    fn entry_key_migration_moves_delivered_records() {
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let feed_data = FeedData {
+            id: "https://feeds.example/blog".to_string(),
+            key: "blog".to_string(),
+            title: "Blog".to_string(),
+            url: "https://blog.example/".to_string(),
+        };
+        let meta_tree = db.open_tree("feed_metadata").unwrap();
+        meta_tree.insert("blog", bincode::serialize(&feed_data).unwrap()).unwrap();
+        let entry_tree = db.open_tree("entries_blog").unwrap();
+        let delivered_tree = db.open_tree("delivered_blog").unwrap();
+        let released_at: DateTime<Utc> = Utc::now();
+        let encoded_release = bincode::serialize(&released_at).unwrap();
+        for (n, published) in [(1, "2010-01-01T00:00:00+00:00"), (2, "2010-02-01T00:00:00+00:00")] {
+            let entry = EntryBuilder::default()
+                .id(format!("{}/{n}", feed_data.id))
+                .published(DateTime::parse_from_rfc3339(published).ok())
+                .build();
+            entry_tree.insert(published, bincode::serialize(&entry).unwrap()).unwrap();
+            if n == 1 {
+                delivered_tree.insert(published, encoded_release.clone()).unwrap();
+            }
+        }
+
+        apply(&db, 1, plan_entry_keys(&db).unwrap()).unwrap();
+        assert_eq!(schema_version(&db).unwrap(), 1);
+        let keys: Vec<String> = entry_tree
+            .iter()
+            .keys()
+            .map(|k| String::from_utf8(k.unwrap().to_vec()).unwrap())
+            .collect();
+        assert_eq!(keys, [
+            "2010-01-01T00:00:00.000000000Z|https://feeds.example/blog/1",
+            "2010-02-01T00:00:00.000000000Z|https://feeds.example/blog/2",
+        ]);
+        assert_eq!(delivered_tree.len(), 1);
+        let delivered = delivered_tree.get(&keys[0]).unwrap().unwrap();
+        assert_eq!(bincode::deserialize::<DateTime<Utc>>(&delivered).unwrap(), released_at);
+        // Running the step again changes nothing.
+        assert!(plan_entry_keys(&db).unwrap().writes.is_empty());
+    }
+
+    #[test]
+    fn decodes_values_from_older_schema_versions() {
+        let released_at = Utc::now();
+        let current = encode(&released_at).unwrap();
+        assert_eq!(decode::<DateTime<Utc>>(&current).unwrap(), released_at);
+
+        let mut older = bincode::serialize(&(SCHEMA_VERSION - 1)).unwrap();
+        older.extend(bincode::serialize(&released_at).unwrap());
+        assert_eq!(decode::<DateTime<Utc>>(&older).unwrap(), released_at);
+
+        let mut newer = bincode::serialize(&(SCHEMA_VERSION + 1)).unwrap();
+        newer.extend(bincode::serialize(&released_at).unwrap());
+        assert!(decode::<DateTime<Utc>>(&newer).is_err());
+        assert!(decode::<DateTime<Utc>>(&[1, 0]).is_err());
+    }
+}
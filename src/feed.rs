@@ -0,0 +1,289 @@
+use std::collections::{HashSet, VecDeque};
+use std::io::Cursor;
+
+use atom_syndication::{
+    CategoryBuilder, ContentBuilder, Entry, EntryBuilder, Feed, LinkBuilder, Person,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Url;
+use reqwest::blocking::Client;
+use rss::Channel;
+
+use crate::common::*;
+
+// Link relations from RFC 5005 that lead further back into a feed's history. "next" is used by
+// paged feeds and "prev-archive" by archived feeds.
+static HISTORY_RELS: &[&str] = &["next", "prev-archive"];
+
+// A single fetched feed document, in whichever syndication format the site publishes.
+pub enum FeedDoc {
+    Atom(Box<Feed>),
+    Rss(Box<Channel>),
+}
+
+impl FeedDoc {
+    pub fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        match Feed::read_from(Cursor::new(body)) {
+            Ok(feed) => Ok(FeedDoc::Atom(Box::new(feed))),
+            Err(_) => Ok(FeedDoc::Rss(Box::new(Channel::read_from(Cursor::new(body))?))),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            FeedDoc::Atom(f) => f.title.value.clone(),
+            FeedDoc::Rss(c) => c.title().to_string(),
+        }
+    }
+
+    pub fn home(&self) -> Option<String> {
+        match self {
+            FeedDoc::Atom(f) => f
+                .links
+                .iter()
+                .find(|l| l.rel == "alternate")
+                .map(|l| l.href.clone()),
+            FeedDoc::Rss(c) => Some(c.link().to_string()).filter(|l| !l.is_empty()),
+        }
+    }
+
+    // Links to older documents in this feed's history, resolved against the document's own URL.
+    pub fn history_links(&self, base: &Url) -> Vec<Url> {
+        let links: Vec<&str> = match self {
+            FeedDoc::Atom(f) => f
+                .links
+                .iter()
+                .filter(|l| HISTORY_RELS.contains(&l.rel.as_str()))
+                .map(|l| l.href.as_str())
+                .collect(),
+            FeedDoc::Rss(c) => c
+                .atom_ext()
+                .map(|a| {
+                    a.links()
+                        .iter()
+                        .filter(|l| HISTORY_RELS.contains(&l.rel()))
+                        .map(|l| l.href())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        links.iter().filter_map(|l| base.join(l).ok()).collect()
+    }
+
+    pub fn entries(&self) -> Vec<Entry> {
+        match self {
+            // Atom requires entry IDs, but not every feed has them.
+            FeedDoc::Atom(f) => f
+                .entries
+                .iter()
+                .map(|e| match e.id.as_str() {
+                    "" => Entry { id: synthetic_id(e), ..e.clone() },
+                    _ => e.clone(),
+                })
+                .collect(),
+            FeedDoc::Rss(c) => c.items().iter().map(item_to_entry).collect(),
+        }
+    }
+}
+
+fn item_to_entry(item: &rss::Item) -> Entry {
+    let link = item.link().unwrap_or_default();
+    // RSS items need only a title or a description, so some have neither a guid nor a link.
+    let id = item
+        .guid()
+        .map(|g| g.value())
+        .filter(|g| !g.is_empty())
+        .or(Some(link).filter(|l| !l.is_empty()))
+        .map_or_else(
+            || {
+                stable_id(&[
+                    item.title().unwrap_or_default(),
+                    item.description().unwrap_or_default(),
+                    item.pub_date().unwrap_or_default(),
+                ])
+            },
+            str::to_string,
+        );
+    let content = item.content().or_else(|| item.description()).map(|v| {
+        ContentBuilder::default()
+            .value(v.to_string())
+            .content_type(Some("html".to_string()))
+            .build()
+    });
+    let authors: Vec<Person> = item
+        .dublin_core_ext()
+        .map(|dc| dc.creators().to_vec())
+        .filter(|c| !c.is_empty())
+        .or_else(|| item.author().map(|a| vec![a.to_string()]))
+        .unwrap_or_default()
+        .drain(..)
+        .map(|name| Person { name, email: None, uri: None })
+        .collect();
+
+    EntryBuilder::default()
+        .title(item.title().unwrap_or_default().to_string())
+        .id(id)
+        .published(item.pub_date().and_then(parse_rfc2822))
+        .authors(authors)
+        .categories(
+            item.categories()
+                .iter()
+                .map(|c| CategoryBuilder::default().term(c.name().to_string()).build())
+                .collect::<Vec<_>>(),
+        )
+        .content(content)
+        .link(
+            LinkBuilder::default()
+                .href(link.to_string())
+                .rel("alternate")
+                .build(),
+        )
+        .build()
+}
+
+// Finds the feed advertised by an HTML page's <link rel="alternate"> tags, if any.
+fn discover_feed_url(base: &Url, html: &str) -> Option<Url> {
+    lazy_static! {
+        static ref LINK_TAG: Regex = Regex::new(r"(?is)<link\b[^>]*>").unwrap();
+        static ref FEED_TYPE: Regex =
+            Regex::new(r#"(?i)type\s*=\s*["']application/(atom|rss)\+xml["']"#).unwrap();
+        static ref HREF: Regex = Regex::new(r#"(?i)href\s*=\s*["']([^"']+)["']"#).unwrap();
+    };
+    LINK_TAG
+        .find_iter(html)
+        .map(|m| m.as_str())
+        .filter(|tag| FEED_TYPE.is_match(tag))
+        .find_map(|tag| HREF.captures(tag))
+        .and_then(|c| base.join(&c[1]).ok())
+}
+
+fn fetch_once(client: &Client, url: &Url) -> anyhow::Result<Vec<u8>> {
+    let resp = client.get(url.clone()).send()?.error_for_status()?;
+    Ok(resp.bytes()?.to_vec())
+}
+
+pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str)
+    -> anyhow::Result<Box<dyn Blog + 'a>>
+{
+    let mut feed_url = Url::parse(url)?;
+    let body = retry_request(config, || fetch_once(client, &feed_url))?;
+    let first_doc = match FeedDoc::parse(&body) {
+        Ok(doc) => doc,
+        Err(_) => {
+            // Not a feed, so try to find one advertised by the page.
+            feed_url = discover_feed_url(&feed_url, &String::from_utf8_lossy(&body))
+                .ok_or_else(|| anyhow::anyhow!("No RSS or Atom feed found at {url}"))?;
+            let body = retry_request(config, || fetch_once(client, &feed_url))?;
+            FeedDoc::parse(&body)?
+        }
+    };
+
+    let title = first_doc.title();
+    let home = first_doc.home().unwrap_or_else(|| url.to_string());
+    let key = sanitize_blog_key(&title);
+    let feed_id = format!("{}/{}", config.feed_url_base, key);
+
+    Ok(Box::new(FeedBlog {
+        feed_url,
+        first_doc,
+        title,
+        home,
+        key,
+        feed_id,
+        config,
+        client,
+    }))
+}
+
+struct FeedBlog<'a> {
+    feed_url: Url,
+    first_doc: FeedDoc,
+    title: String,
+    home: String,
+    key: String,
+    feed_id: String,
+    config: &'a Config,
+    client: &'a Client,
+}
+
+impl Blog for FeedBlog<'_> {
+    fn blog_type(&self) -> BlogType {
+        BlogType::Feed
+    }
+
+    fn feed_data(&self) -> FeedData {
+        FeedData {
+            id: self.feed_id.clone(),
+            key: self.key.clone(),
+            title: self.title.clone(),
+            url: self.home.clone(),
+        }
+    }
+
+    fn entries(&self) -> anyhow::Result<Vec<Entry>> {
+        let mut posts: Vec<Entry> = Vec::new();
+        let mut seen_ids: HashSet<String> = HashSet::new();
+        let mut visited: HashSet<Url> = HashSet::from([self.feed_url.clone()]);
+        let mut pending: VecDeque<Url> = VecDeque::new();
+
+        println!(r#"Scraping "{}" (following feed archives)"#, &self.title);
+        let pb = init_progress_bar(1);
+        let mut add_doc = |doc: &FeedDoc, doc_url: &Url, pending: &mut VecDeque<Url>| {
+            for link in doc.history_links(doc_url) {
+                if visited.insert(link.clone()) {
+                    pb.inc_length(1);
+                    pending.push_back(link);
+                }
+            }
+            posts.extend(doc.entries().drain(..).filter(|e| seen_ids.insert(e.id.clone())));
+            pb.inc(1);
+        };
+
+        add_doc(&self.first_doc, &self.feed_url, &mut pending);
+        while let Some(doc_url) = pending.pop_front() {
+            let body = retry_request(self.config, || fetch_once(self.client, &doc_url))?;
+            add_doc(&FeedDoc::parse(&body)?, &doc_url, &mut pending);
+        }
+        pb.finish();
+
+        Ok(posts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_without_guid_or_link_get_distinct_stable_ids() {
+        let rss = br#"<?xml version="1.0"?>
+<rss version="2.0"><channel>
+<title>T</title><link>http://t.example/</link><description>d</description>
+<item><title>One</title><pubDate>Fri, 01 Jan 2010 10:00:00 +0000</pubDate></item>
+<item><title>Two</title><pubDate>Fri, 01 Jan 2010 10:00:00 +0000</pubDate></item>
+<item><description>Untitled</description><guid></guid><link></link></item>
+</channel></rss>"#;
+        let entries = FeedDoc::parse(rss).unwrap().entries();
+        let ids: HashSet<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids.iter().all(|id| id.starts_with("urn:blog-replay:")));
+
+        let again = FeedDoc::parse(rss).unwrap().entries();
+        assert!(entries.iter().zip(&again).all(|(a, b)| a.id == b.id));
+    }
+
+    #[test]
+    fn atom_entries_without_ids_get_distinct_stable_ids() {
+        let atom = br#"<?xml version="1.0"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+<title>T</title><id>urn:t</id><updated>2010-01-01T10:00:00Z</updated>
+<entry><title>One</title><id></id><updated>2010-01-01T10:00:00Z</updated></entry>
+<entry><title>Two</title><id></id><updated>2010-01-01T10:00:00Z</updated></entry>
+</feed>"#;
+        let entries = FeedDoc::parse(atom).unwrap().entries();
+        assert_eq!(entries.len(), 2);
+        assert_ne!(entries[0].id, entries[1].id);
+        assert!(entries.iter().all(|e| e.id.starts_with("urn:blog-replay:")));
+    }
+}
@@ -0,0 +1,222 @@
+use atom_syndication::{
+    CategoryBuilder, ContentBuilder, Entry, EntryBuilder, LinkBuilder, Person,
+};
+use reqwest::Url;
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::Deserialize;
+
+use crate::common::*;
+
+// Ghost 5.x requires clients to declare which API version they expect.
+static ACCEPT_VERSION: &str = "v5.0";
+
+// Parsed from the Ghost Content API settings endpoint
+#[derive(Deserialize, Debug)]
+struct SettingsResponse {
+    settings: GhostJson,
+}
+
+#[derive(Deserialize, Debug)]
+struct GhostJson {
+    title: String,
+    url: String,
+}
+
+pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str)
+    -> anyhow::Result<Box<dyn Blog + 'a>>
+{
+    let site_url = Url::parse(url)?;
+    let host = site_url.host_str().ok_or_else(|| anyhow::anyhow!("URL has no host"))?;
+    // Content API keys are issued per site, so we can't probe a Ghost blog without one.
+    let api_key = config
+        .ghost_content_api_keys
+        .get(host)
+        .ok_or_else(|| anyhow::anyhow!("No Ghost Content API key configured for {host}"))?;
+
+    let api_url = site_url.join("ghost/api/content/")?;
+    let settings_url = api_url.join("settings/")?;
+    let settings: SettingsResponse = retry_request(config, || {
+        Ok(client
+                .get(settings_url.clone())
+                .header("Accept-Version", ACCEPT_VERSION)
+                .query(&[("key", api_key)])
+                .send()?
+                .error_for_status()?
+                .json()?)
+    })?;
+    let api_json = settings.settings;
+
+    let key = sanitize_blog_key(&api_json.title);
+    let feed_id = format!("{}/{}", config.feed_url_base, key);
+
+    Ok(Box::new(GhostBlog {
+        api_json,
+        posts_api_url: api_url.join("posts/")?,
+        pages_api_url: api_url.join("pages/")?,
+        api_key,
+        key,
+        feed_id,
+        config,
+        client,
+    }))
+}
+
+struct GhostBlog<'a> {
+    api_json: GhostJson,
+    posts_api_url: Url,
+    pages_api_url: Url,
+    api_key: &'a String,
+    key: String,
+    feed_id: String,
+    config: &'a Config,
+    client: &'a Client,
+}
+
+impl Blog for GhostBlog<'_> {
+    fn blog_type(&self) -> BlogType {
+        BlogType::Ghost
+    }
+
+    fn feed_data(&self) -> FeedData {
+        FeedData {
+            id: self.feed_id.clone(),
+            key: self.key.clone(),
+            title: self.api_json.title.clone(),
+            url: self.api_json.url.clone(),
+        }
+    }
+
+    fn entries(&self) -> anyhow::Result<Vec<Entry>> {
+        let mut posts: Vec<Entry> = Vec::new();
+
+        for (url, display) in &[(&self.posts_api_url, "posts"), (&self.pages_api_url, "pages")] {
+            let mut api_page = 1;
+            let mut pb: Option<indicatif::ProgressBar> = None;
+            loop {
+                let resp = retry_request(self.config, || self.get_page_once(url, api_page))?;
+                let pagination = &resp.meta.pagination;
+                if api_page == 1 {
+                    println!(
+                        r#"Scraping "{}" ({} {})"#,
+                        &self.api_json.title, pagination.total, display
+                    );
+                    pb = Some(init_progress_bar(pagination.total.try_into().unwrap()));
+                }
+                if let Some(pb) = pb.as_ref() { pb.inc(resp.items.len().try_into().unwrap()) };
+                posts.extend(resp.items.iter().map(|p| self.post_to_entry(p)));
+                match pagination.next {
+                    Some(next) => api_page = next,
+                    None => break,
+                }
+            }
+            if let Some(pb) = pb { pb.finish() };
+        }
+
+        Ok(posts)
+    }
+}
+
+impl GhostBlog<'_> {
+    fn request(&self, api_url: &Url) -> RequestBuilder {
+        self.client
+            .get(api_url.clone())
+            .header("Accept-Version", ACCEPT_VERSION)
+            .query(&[("key", self.api_key)])
+    }
+
+    fn get_page_once(&self, api_url: &Url, page: usize) -> anyhow::Result<BrowseResponse> {
+        let resp = self
+            .request(api_url)
+            .query(&[
+                ("include", "authors,tags"),
+                ("formats", "html"),
+                ("limit", "15"),
+                ("page", &format!("{page}")),
+            ])
+            .send()?
+            .error_for_status()?;
+
+        Ok(resp.json()?)
+    }
+
+    fn post_to_entry(&self, post: &Post) -> Entry {
+        let content = post.html.as_ref().map(|v| {
+            ContentBuilder::default()
+                .value(v.clone())
+                .content_type(Some("html".to_string()))
+                .build()
+        });
+
+        EntryBuilder::default()
+            .title(post.title.clone())
+            .id(format!("{}/{}", self.feed_id, post.id))
+            .published(post.published_at.as_deref().and_then(parse_datetime))
+            .authors(
+                post.authors
+                    .iter()
+                    .map(|a| Person {
+                        name: a.name.clone(),
+                        email: None,
+                        uri: a.url.clone(),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .categories(
+                post.tags
+                    .iter()
+                    .map(|t| CategoryBuilder::default().term(t.name.clone()).build())
+                    .collect::<Vec<_>>(),
+            )
+            .content(content)
+            .link(
+                LinkBuilder::default()
+                    .href(post.url.clone())
+                    .rel("alternate")
+                    .build(),
+            )
+            .build()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct Author {
+    name: String,
+    url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Tag {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Post {
+    id: String,
+    url: String,
+    title: String,
+    html: Option<String>,
+    published_at: Option<String>,
+    #[serde(default)]
+    authors: Vec<Author>,
+    #[serde(default)]
+    tags: Vec<Tag>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Pagination {
+    total: usize,
+    next: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Meta {
+    pagination: Pagination,
+}
+
+// The posts and pages endpoints return the same shape under different top-level keys.
+#[derive(Deserialize, Debug)]
+struct BrowseResponse {
+    #[serde(alias = "posts", alias = "pages")]
+    items: Vec<Post>,
+    meta: Meta,
+}
@@ -1,16 +1,25 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fs::{File, Permissions};
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 
-use atom_syndication::{Entry, Generator};
-use chrono::Utc;
+use atom_syndication::{ContentBuilder, Entry, Generator};
+use chrono::{DateTime, FixedOffset, Local, Utc};
 use clap::clap_app;
+use sled::transaction::{ConflictableTransactionError, Transactional};
 
 mod blogger;
 mod common;
+mod db;
+mod feed;
+mod ghost;
+mod markdown;
+mod substack;
+mod tumblr;
+mod wayback;
 mod wordpress;
+mod wxr;
 
 use common::*;
 
@@ -20,101 +29,721 @@ static USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_V
 
 fn do_scrape(
     url: &str,
+    blog_type: Option<BlogType>,
+    strict: bool,
+    comments: bool,
     config: &Config,
     gen: &Generator,
     db_path: &Path,
 ) -> Result<(), Box<dyn Error>> {
-    let db = sled::open(db_path)?;
     let client = reqwest::blocking::ClientBuilder::new()
         .user_agent(USER_AGENT)
         .build()?;
 
-    let blog = common::get_blog(config, &client, url)?;
-    let feed_data = blog.feed_data();
+    let blog = common::get_blog(config, &client, url, blog_type)?;
+    println!("Detected {:?} blog", blog.blog_type());
+    store_blog(blog.as_ref(), strict, comments, config, gen, db_path)
+}
+
+fn do_import_wxr(
+    file: &Path,
+    config: &Config,
+    gen: &Generator,
+    db_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let blog = wxr::load_blog(config, file)?;
+    store_blog(blog.as_ref(), false, false, config, gen, db_path)
+}
+
+fn do_import_blogger(
+    file: &Path,
+    config: &Config,
+    gen: &Generator,
+    db_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let blog = blogger::load_archive(config, file)?;
+    store_blog(blog.as_ref(), false, false, config, gen, db_path)
+}
+
+fn do_import_dir(
+    dir: &Path,
+    base_url: Option<&str>,
+    title: Option<&str>,
+    config: &Config,
+    gen: &Generator,
+    db_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let blog = markdown::load_blog(config, dir, base_url, title)?;
+    store_blog(blog.as_ref(), false, false, config, gen, db_path)
+}
+
+fn store_blog(
+    blog: &dyn Blog,
+    strict: bool,
+    comments: bool,
+    config: &Config,
+    gen: &Generator,
+    db_path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let db = db::open(db_path)?;
+    let feed_data = resolve_renamed(&db, blog.feed_data())?;
     let entries = blog.entries()?;
+    if let Some(expected) = blog.expected_len() {
+        if entries.len() != expected {
+            let msg = format!(
+                "Scraped {} posts and pages, but the blog reports {}",
+                entries.len(),
+                expected
+            );
+            if strict {
+                return Err(format!("{msg}; refusing to store a partial archive").into());
+            }
+            println!("WARNING: {msg}");
+        }
+    }
 
     let meta_tree = db.open_tree("feed_metadata")?;
-    meta_tree.insert(&feed_data.key, bincode::serialize(&feed_data)?)?;
-    let entry_tree = db.open_tree(format!("entries_{}", feed_data.key))?;
-    for entry in &entries {
-        entry_tree.insert(
-            entry.published.unwrap_or(entry.updated).to_rfc3339(),
-            bincode::serialize(&entry)?,
-        )?;
+    let rescrape = meta_tree.contains_key(&feed_data.key)?;
+    meta_tree.insert(&feed_data.key, db::encode(&feed_data)?)?;
+    let merged = merge_entries(config, &feed_data, gen, &db, &entries)?;
+    if comments {
+        if blog.supports_comments() {
+            store_comments(blog, &entries, &feed_data, &db)?;
+        } else {
+            println!("WARNING: comments can't be scraped from {:?} blogs", blog.blog_type());
+        }
+    }
+
+    // Re-scraping only catches up with the blog; the replay carries on where it was.
+    if rescrape {
+        println!(
+            "\nUpdated {}: {} new and {} changed posts",
+            feed_data.key,
+            merged.new.len(),
+            merged.changed.len()
+        );
+        for entry in &merged.new {
+            println!("   new: {}", entry.title.value);
+        }
+        for entry in &merged.changed {
+            println!("   changed: {}", entry.title.value);
+        }
+        return Ok(());
     }
-    // Remove the corresponding generated feed if present, so that we don't duplicate entries.
-    let _ = std::fs::remove_file(path_from_feed_data(config, &feed_data));
 
-    // Generate this feed and tell us where it's located.
-    generate_feed(config, &feed_data, gen, &db)?;
+    // Start the replay with the first entry, and tell us where the feed is located.
+    generate_feed(config, &feed_data, gen, &db, Release::Count(1), Utc::now())?;
     println!("\nSUCCESS: replay located at {}.atom", feed_data.id);
     Ok(())
 }
 
+// A renamed blog is still found under the key its source gives it, and keeps its new key and ID.
+fn resolve_renamed(db: &sled::Db, mut feed_data: FeedData) -> Result<FeedData, Box<dyn Error>> {
+    let alias_tree = db.open_tree("renamed_blogs")?;
+    if let Some(key) = alias_tree.get(&feed_data.key)? {
+        let renamed = get_feed_data(db, &db::decode::<String>(&key)?)?;
+        feed_data.key = renamed.key;
+        feed_data.id = renamed.id;
+    }
+    Ok(feed_data)
+}
+
+// Stores scraped entries, matching them to the ones already stored by Atom ID. New entries are
+// added, and edited ones are updated in place so that the replay position isn't disturbed.
+fn merge_entries<'e>(
+    config: &Config,
+    feed_data: &FeedData,
+    gen: &Generator,
+    db: &sled::Db,
+    entries: &'e [Entry],
+) -> Result<Merged<'e>, Box<dyn Error>> {
+    let entry_tree = db.open_tree(format!("entries_{}", feed_data.key))?;
+    let delivered_tree = db.open_tree(format!("delivered_{}", feed_data.key))?;
+    let mut stored: HashMap<String, (sled::IVec, Entry)> = HashMap::new();
+    for item in entry_tree.iter() {
+        let (key, val) = item?;
+        let entry: Entry = db::decode(&val)?;
+        stored.insert(entry.id.clone(), (key, entry));
+    }
+    // Older versions deleted entries from the DB once delivered, so the feed file may be the only
+    // record of them.
+    let feed = read_or_create_feed(path_from_feed_data(config, feed_data), gen, feed_data)?;
+    let in_feed: HashMap<&str, DateTime<Utc>> = feed
+        .entries
+        .iter()
+        .map(|e| (e.id.as_str(), e.updated.with_timezone(&Utc)))
+        .collect();
+    // With max_entries, the oldest released entries have also rotated out of the feed. Anything
+    // older than the newest entry still in it must be one of those, released before every entry
+    // still in the feed. Once delivery records exist they are the whole story, so entries found
+    // later, like backdated posts, are new.
+    let legacy = !in_feed.is_empty() && delivered_tree.is_empty();
+    let earliest_in_feed = in_feed.values().min().copied().filter(|_| legacy);
+    let newest_in_feed = entries
+        .iter()
+        .filter(|e| in_feed.contains_key(e.id.as_str()))
+        .map(entry_key)
+        .max();
+
+    let mut new = Vec::new();
+    let mut changed = Vec::new();
+    for entry in entries {
+        match stored.get(&entry.id) {
+            Some((key, old)) => {
+                // Sources without modification times give every scrape a new `updated`.
+                let mut unchanged = entry.clone();
+                unchanged.set_updated(old.updated);
+                if unchanged != *old {
+                    entry_tree.insert(key, db::encode(entry)?)?;
+                    changed.push(entry);
+                }
+            }
+            None => {
+                let key = entry_key(entry);
+                entry_tree.insert(key.as_bytes(), db::encode(entry)?)?;
+                let released_at = in_feed.get(entry.id.as_str()).copied().or_else(|| {
+                    earliest_in_feed.filter(|_| newest_in_feed.as_ref().is_some_and(|n| key < *n))
+                });
+                match released_at {
+                    Some(released_at) => {
+                        delivered_tree.insert(key.as_bytes(), db::encode(&released_at)?)?;
+                    }
+                    None => new.push(entry),
+                }
+            }
+        }
+    }
+    Ok(Merged { new, changed })
+}
+
+struct Merged<'e> {
+    new: Vec<&'e Entry>,
+    changed: Vec<&'e Entry>,
+}
+
+fn store_comments(
+    blog: &dyn Blog,
+    entries: &[Entry],
+    feed_data: &FeedData,
+    db: &sled::Db,
+) -> Result<(), Box<dyn Error>> {
+    let comment_tree = db.open_tree(format!("comments_{}", feed_data.key))?;
+    println!("Scraping comments");
+    let pb = init_progress_bar(entries.len().try_into().unwrap());
+    let mut count = 0;
+    for entry in entries {
+        let comments = blog.comments(entry)?;
+        if !comments.is_empty() {
+            count += comments.len();
+            comment_tree.insert(&entry.id, db::encode(&comments)?)?;
+        }
+        pb.inc(1);
+    }
+    pb.finish();
+    println!("Stored {count} comments");
+    Ok(())
+}
+
+// Releases a blog's oldest undelivered entries into its feed, for as long as they're due, and
+// records `now` as the blog's last release time if any were released. Entries stay in the DB, so
+// missing feed files are rebuilt first.
 fn generate_feed(
     config: &Config,
     feed_data: &FeedData,
     gen: &Generator,
     db: &sled::Db,
+    release: Release,
+    now: DateTime<Utc>,
+) -> Result<usize, Box<dyn Error>> {
+    rebuild_missing_feeds(config, feed_data, gen, db)?;
+
+    let entry_tree = db.open_tree(format!("entries_{}", feed_data.key))?;
+    let delivered_tree = db.open_tree(format!("delivered_{}", feed_data.key))?;
+    let comment_tree = db.open_tree(format!("comments_{}", feed_data.key))?;
+    let released_at = Utc::now();
+    let mut entries = Vec::new();
+    let mut comment_entries = Vec::new();
+    for item in entry_tree.iter() {
+        let (key, val) = item?;
+        if delivered_tree.contains_key(&key)? {
+            continue;
+        }
+        let entry: Entry = db::decode(&val)?;
+        let published = entry.published.unwrap_or(entry.updated);
+        let due = match release {
+            Release::Count(count) => entries.len() < count,
+            Release::PublishedBy(time) => published <= time,
+            Release::PublishedBetween(after, _) if published <= after => continue,
+            Release::PublishedBetween(_, by) => published <= by,
+        };
+        if !due {
+            break;
+        }
+        delivered_tree.insert(&key, db::encode(&released_at)?)?;
+        let (entry, comments) = replayed_entry(config, &comment_tree, entry, released_at)?;
+        entries.push(entry);
+        comment_entries.extend(comments);
+    }
+
+    let released = entries.len();
+    if !comment_entries.is_empty() {
+        write_feed_entries(config, &comments_feed_data(feed_data), gen, comment_entries)?;
+    }
+    if released > 0 {
+        write_feed_entries(config, feed_data, gen, entries)?;
+        let state_tree = db.open_tree("replay_state")?;
+        state_tree.insert(&feed_data.key, db::encode(&now)?)?;
+    }
+
+    Ok(released)
+}
+
+// Prepares a stored entry for the feed as it was released, along with the entries for its
+// comments when those go to a separate feed.
+fn replayed_entry(
+    config: &Config,
+    comment_tree: &sled::Tree,
+    mut entry: Entry,
+    released_at: DateTime<Utc>,
+) -> Result<(Entry, Vec<Entry>), Box<dyn Error>> {
+    entry.set_updated(released_at);
+    let mut comment_entries = Vec::new();
+    if let Some(val) = comment_tree.get(&entry.id)? {
+        let mut comments: Vec<Comment> = db::decode(&val)?;
+        comments.sort_by_key(|c| c.published);
+        match config.comment_mode {
+            CommentMode::Inline => append_comments(&mut entry, &comments),
+            CommentMode::Feed => comment_entries.extend(comments.iter().map(|c| {
+                let mut comment_entry = comment_entry(&entry, c);
+                comment_entry.set_updated(released_at);
+                comment_entry
+            })),
+        }
+    }
+    Ok((entry, comment_entries))
+}
+
+// Recreates deleted feed files from the entries that have already been delivered.
+fn rebuild_missing_feeds(
+    config: &Config,
+    feed_data: &FeedData,
+    gen: &Generator,
+    db: &sled::Db,
+) -> Result<(), Box<dyn Error>> {
+    let comments_data = comments_feed_data(feed_data);
+    let feed_missing = !path_from_feed_data(config, feed_data).exists();
+    let comments_missing = config.comment_mode == CommentMode::Feed
+        && !path_from_feed_data(config, &comments_data).exists();
+    let delivered_tree = db.open_tree(format!("delivered_{}", feed_data.key))?;
+    if (!feed_missing && !comments_missing) || delivered_tree.is_empty() {
+        return Ok(());
+    }
+
+    let entry_tree = db.open_tree(format!("entries_{}", feed_data.key))?;
+    let comment_tree = db.open_tree(format!("comments_{}", feed_data.key))?;
+    let mut entries = Vec::new();
+    let mut comment_entries = Vec::new();
+    for item in entry_tree.iter() {
+        let (key, val) = item?;
+        let Some(released_at) = delivered_tree.get(&key)? else { continue };
+        let released_at: DateTime<Utc> = db::decode(&released_at)?;
+        let (entry, comments) =
+            replayed_entry(config, &comment_tree, db::decode(&val)?, released_at)?;
+        entries.push(entry);
+        comment_entries.extend(comments);
+    }
+
+    if feed_missing && !entries.is_empty() {
+        println!("Rebuilding {}.atom from {} delivered entries", feed_data.key, entries.len());
+        write_feed_entries(config, feed_data, gen, entries)?;
+    }
+    if comments_missing && !comment_entries.is_empty() {
+        write_feed_entries(config, &comments_data, gen, comment_entries)?;
+    }
+    Ok(())
+}
+
+// The companion feed that comments are published to when comment_mode is "feed".
+fn comments_feed_data(feed_data: &FeedData) -> FeedData {
+    FeedData {
+        id: format!("{}-comments", feed_data.id),
+        key: format!("{}-comments", feed_data.key),
+        title: format!("{} comments", feed_data.title),
+        url: feed_data.url.clone(),
+    }
+}
+
+fn append_comments(entry: &mut Entry, comments: &[Comment]) {
+    let html = comments_html(comments);
+    match entry.content.as_mut() {
+        Some(content) => content.value = Some(content.value.take().unwrap_or_default() + &html),
+        None => entry.set_content(
+            ContentBuilder::default()
+                .value(html)
+                .content_type(Some("html".to_string()))
+                .build(),
+        ),
+    }
+}
+
+// Adds entries to the end of a generated feed, dropping the oldest beyond max_entries.
+fn write_feed_entries(
+    config: &Config,
+    feed_data: &FeedData,
+    gen: &Generator,
+    entries: Vec<Entry>,
 ) -> Result<(), Box<dyn Error>> {
     let feed_path = path_from_feed_data(config, feed_data);
     let mut feed = read_or_create_feed(&feed_path, gen, feed_data)?;
-    let entry_tree = db.open_tree(format!("entries_{}", feed_data.key))?;
-    let item = entry_tree.pop_min()?;
-    if let Some((_, val)) = item {
-        let mut entry: Entry = bincode::deserialize(&val)?;
-        entry.set_updated(Utc::now());
-        feed.entries.push(entry);
-        if let Some(max_entries) = config.max_entries {
-            let len = feed.entries.len();
-            if len > max_entries {
-                feed.entries.rotate_left(len - max_entries);
-                feed.entries.truncate(max_entries);
-            }
+    feed.entries.extend(entries);
+    if let Some(max_entries) = config.max_entries {
+        let len = feed.entries.len();
+        if len > max_entries {
+            feed.entries.rotate_left(len - max_entries);
+            feed.entries.truncate(max_entries);
         }
-        feed.set_updated(Utc::now());
-        feed.write_to(File::create(&feed_path)?)?;
-        std::fs::set_permissions(&feed_path, Permissions::from_mode(0o644))?;
     }
+    feed.set_updated(Utc::now());
+    feed.write_to(File::create(&feed_path)?)?;
+    std::fs::set_permissions(&feed_path, Permissions::from_mode(0o644))?;
 
     Ok(())
 }
 
 fn do_generate(config: &Config, gen: &Generator, db_path: &Path) -> Result<(), Box<dyn Error>> {
-    let db = sled::open(db_path)?;
+    let db = db::open(db_path)?;
     let meta_tree = db.open_tree("feed_metadata")?;
+    let now = Utc::now();
     for (_, meta) in meta_tree.iter().flatten() {
-        let feed_data: FeedData = bincode::deserialize(&meta)?;
-        generate_feed(config, &feed_data, gen, &db)?;
+        let feed_data: FeedData = db::decode(&meta)?;
+        // Blogs without a schedule release one entry per run.
+        let due = match get_schedule(&db, &feed_data.key)? {
+            Some(schedule) => schedule.due(get_last_release(&db, &feed_data.key)?, now),
+            None => Some((Release::Count(1), now)),
+        };
+        if let Some((release, release_time)) = due {
+            generate_feed(config, &feed_data, gen, &db, release, release_time)?;
+        }
     }
 
     Ok(())
 }
 
+fn get_schedule(db: &sled::Db, key: &str) -> Result<Option<Schedule>, Box<dyn Error>> {
+    let schedule_tree = db.open_tree("replay_schedules")?;
+    Ok(schedule_tree.get(key)?.map(|v| db::decode(&v)).transpose()?)
+}
+
+fn get_last_release(db: &sled::Db, key: &str) -> Result<Option<DateTime<Utc>>, Box<dyn Error>> {
+    let state_tree = db.open_tree("replay_state")?;
+    Ok(state_tree.get(key)?.map(|v| db::decode(&v)).transpose()?)
+}
+
+// The original publication date of the next entry to be replayed.
+fn next_published(db: &sled::Db, key: &str)
+    -> Result<Option<DateTime<FixedOffset>>, Box<dyn Error>>
+{
+    let entry_tree = db.open_tree(format!("entries_{key}"))?;
+    let delivered_tree = db.open_tree(format!("delivered_{key}"))?;
+    for item in entry_tree.iter() {
+        let (key, val) = item?;
+        if !delivered_tree.contains_key(&key)? {
+            let entry: Entry = db::decode(&val)?;
+            return Ok(Some(entry.published.unwrap_or(entry.updated)));
+        }
+    }
+    Ok(None)
+}
+
+fn do_schedule(db_path: &Path, key: &str, spec: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let db = db::open(db_path)?;
+    let meta_tree = db.open_tree("feed_metadata")?;
+    if !meta_tree.contains_key(key)? {
+        return Err(format!("No blog with key {key}").into());
+    }
+    let schedule_tree = db.open_tree("replay_schedules")?;
+    match spec {
+        None => match get_schedule(&db, key)? {
+            Some(schedule) => println!("{key}: {schedule}"),
+            None => println!("{key}: 1 every generate run"),
+        },
+        Some("none" | "default") => {
+            schedule_tree.remove(key)?;
+            println!("{key}: 1 every generate run");
+        }
+        Some(spec) => {
+            let schedule = Schedule::parse(spec, Utc::now(), next_published(&db, key)?)?;
+            schedule_tree.insert(key, db::encode(&schedule)?)?;
+            println!("{key}: {schedule}");
+        }
+    }
+    Ok(())
+}
+
 fn do_ls(db_path: &Path, long: bool, blogs: &HashSet<&str>) -> Result<(), Box<dyn Error>> {
-    let db = sled::open(db_path)?;
+    let db = db::open(db_path)?;
     let meta_tree = db.open_tree("feed_metadata")?;
     for (key, meta) in meta_tree.iter().flatten() {
         let key = String::from_utf8(key.to_vec())?;
         if ! blogs.is_empty() && ! blogs.contains(key.as_str()) {
             continue;
         }
-        let feed_data: FeedData = bincode::deserialize(&meta)?;
+        let feed_data: FeedData = db::decode(&meta)?;
         let entry_tree = db.open_tree(format!("entries_{}", feed_data.key))?;
+        let delivered_tree = db.open_tree(format!("delivered_{}", feed_data.key))?;
+        let schedule = get_schedule(&db, &feed_data.key)?
+            .map(|s| format!(", {s}"))
+            .unwrap_or_default();
+        let counts = format!("{} posts ({} delivered{})",
+            entry_tree.len(), delivered_tree.len(), schedule);
         if long {
-            println!("{} \"{}\" ({}): {} posts",
-                feed_data.key, feed_data.title, feed_data.id, entry_tree.len());
-            for (_, val) in entry_tree.iter().flatten() {
-                let entry: Entry = bincode::deserialize(&val)?;
+            println!("{} \"{}\" ({}): {}", feed_data.key, feed_data.title, feed_data.id, counts);
+            // Only list the posts still waiting to be replayed.
+            for (key, val) in entry_tree.iter().flatten() {
+                if delivered_tree.contains_key(&key)? {
+                    continue;
+                }
+                let entry: Entry = db::decode(&val)?;
                 println!("   {}", entry.title.value);
             }
         } else {
-            println!("{}: {} posts", feed_data.key, entry_tree.len());
+            println!("{}: {}", feed_data.key, counts);
         }
     }
     Ok(())
 }
 
+fn do_status(db_path: &Path, blogs: &HashSet<&str>) -> Result<(), Box<dyn Error>> {
+    let db = db::open(db_path)?;
+    let meta_tree = db.open_tree("feed_metadata")?;
+    let now = Utc::now();
+    let format_date = |d: DateTime<FixedOffset>| d.with_timezone(&Local).format("%F %R").to_string();
+    for (key, meta) in meta_tree.iter().flatten() {
+        let key = String::from_utf8(key.to_vec())?;
+        if ! blogs.is_empty() && ! blogs.contains(key.as_str()) {
+            continue;
+        }
+        let feed_data: FeedData = db::decode(&meta)?;
+        let entry_tree = db.open_tree(format!("entries_{}", feed_data.key))?;
+        let delivered_tree = db.open_tree(format!("delivered_{}", feed_data.key))?;
+        let schedule = get_schedule(&db, &key)?;
+        println!("{}:", feed_data.key);
+        match &schedule {
+            Some(schedule) => println!("   schedule: {schedule}"),
+            None => println!("   schedule: 1 every generate run"),
+        }
+        match get_last_release(&db, &key)? {
+            Some(last) => println!("   last release: {}", format_date(last.into())),
+            None => println!("   last release: never"),
+        }
+        if let Some(reached) = schedule.and_then(|s| s.reached(now)) {
+            println!("   replay reached: {}", format_date(reached));
+        }
+        match next_published(&db, &key)? {
+            Some(next) => println!(
+                "   remaining: {} entries, the next originally published {}",
+                entry_tree.len().saturating_sub(delivered_tree.len()),
+                format_date(next)
+            ),
+            None => println!("   remaining: none"),
+        }
+    }
+    Ok(())
+}
+
+// Trees that hold one blog's data, named "<prefix>_<key>".
+static BLOG_TREES: &[&str] = &["entries", "delivered", "comments"];
+
+fn get_feed_data(db: &sled::Db, key: &str) -> Result<FeedData, Box<dyn Error>> {
+    let meta_tree = db.open_tree("feed_metadata")?;
+    match meta_tree.get(key)? {
+        Some(meta) => Ok(db::decode(&meta)?),
+        None => Err(format!("No blog with key {key}").into()),
+    }
+}
+
+// Deletes a blog's generated feeds, including the comments feed if there is one.
+fn remove_feed_files(config: &Config, feed_data: &FeedData) -> Result<(), Box<dyn Error>> {
+    for data in [feed_data.clone(), comments_feed_data(feed_data)] {
+        match std::fs::remove_file(path_from_feed_data(config, &data)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+fn do_rm(config: &Config, db_path: &Path, key: &str) -> Result<(), Box<dyn Error>> {
+    let db = db::open(db_path)?;
+    let feed_data = get_feed_data(&db, key)?;
+    for prefix in BLOG_TREES {
+        db.drop_tree(format!("{prefix}_{key}"))?;
+    }
+    for tree in ["replay_schedules", "replay_state", "feed_metadata"] {
+        db.open_tree(tree)?.remove(key)?;
+    }
+    let alias_tree = db.open_tree("renamed_blogs")?;
+    for item in alias_tree.iter() {
+        let (source_key, val) = item?;
+        if db::decode::<String>(&val)? == key {
+            alias_tree.remove(source_key)?;
+        }
+    }
+    remove_feed_files(config, &feed_data)?;
+    println!("Removed {key}");
+    Ok(())
+}
+
+// Entry IDs are left alone, and the old key is recorded as an alias for the new one, so that
+// re-scrapes update the renamed blog and still recognize its entries.
+fn do_rename(
+    config: &Config,
+    gen: &Generator,
+    db_path: &Path,
+    old: &str,
+    new: &str,
+) -> Result<(), Box<dyn Error>> {
+    let db = db::open(db_path)?;
+    let old_data = get_feed_data(&db, old)?;
+    let sanitized = sanitize_blog_key(new);
+    if sanitized != new {
+        return Err(format!("{new} isn't a valid blog key; try {sanitized}").into());
+    }
+    let meta_tree = db.open_tree("feed_metadata")?;
+    if meta_tree.contains_key(new)? {
+        return Err(format!("A blog with key {new} already exists").into());
+    }
+    if let Some(renamed) = db.open_tree("renamed_blogs")?.get(new)? {
+        let renamed: String = db::decode(&renamed)?;
+        if renamed != old {
+            return Err(format!("{new} is the original key of {renamed}, so it can't be reused")
+                .into());
+        }
+    }
+    let new_data = FeedData {
+        id: format!("{}/{}", config.feed_url_base, new),
+        key: new.to_string(),
+        ..old_data.clone()
+    };
+
+    // Everything moves in one transaction, so that an interrupted rename leaves the blog as it
+    // was. The old trees are only dropped once it has committed.
+    let mut copies = Vec::new();
+    for prefix in BLOG_TREES {
+        let items: Vec<(sled::IVec, sled::IVec)> =
+            db.open_tree(format!("{prefix}_{old}"))?.iter().collect::<Result<_, _>>()?;
+        copies.push(items);
+    }
+    let alias_tree = db.open_tree("renamed_blogs")?;
+    let mut redirected = Vec::new();
+    for item in alias_tree.iter() {
+        let (source_key, val) = item?;
+        if db::decode::<String>(&val)? == old {
+            redirected.push(source_key);
+        }
+    }
+    let mut trees = vec![
+        meta_tree,
+        db.open_tree("replay_schedules")?,
+        db.open_tree("replay_state")?,
+        alias_tree,
+    ];
+    for prefix in BLOG_TREES {
+        trees.push(db.open_tree(format!("{prefix}_{new}"))?);
+    }
+    let encoded_data = db::encode(&new_data)?;
+    let encoded_key = db::encode(&new.to_string())?;
+    trees
+        .as_slice()
+        .transaction(|tx| {
+            let (meta, moved, aliases) = (&tx[0], &tx[1..3], &tx[3]);
+            for (tree, items) in tx[4..].iter().zip(&copies) {
+                for (key, val) in items {
+                    tree.insert(key, val.clone())?;
+                }
+            }
+            for tree in moved {
+                if let Some(val) = tree.remove(old)? {
+                    tree.insert(new, val)?;
+                }
+            }
+            meta.insert(new, encoded_data.clone())?;
+            meta.remove(old)?;
+            for source_key in &redirected {
+                aliases.insert(source_key, encoded_key.clone())?;
+            }
+            aliases.insert(old, encoded_key.clone())?;
+            // Renaming a blog back to its original key makes the alias unnecessary.
+            aliases.remove(new)?;
+            Ok::<_, ConflictableTransactionError<()>>(())
+        })
+        .map_err(|e| format!("Renaming {old} to {new} failed: {e:?}"))?;
+    db.flush()?;
+    for prefix in BLOG_TREES {
+        db.drop_tree(format!("{prefix}_{old}"))?;
+    }
+
+    move_feed_file(config, gen, &old_data, &new_data)?;
+    move_feed_file(config, gen, &comments_feed_data(&old_data), &comments_feed_data(&new_data))?;
+    println!("Renamed {old} to {new}; the replay is now located at {}.atom", new_data.id);
+    Ok(())
+}
+
+// Moves a generated feed to its new location, pointing its ID and self link there too.
+fn move_feed_file(
+    config: &Config,
+    gen: &Generator,
+    old_data: &FeedData,
+    new_data: &FeedData,
+) -> Result<(), Box<dyn Error>> {
+    let old_path = path_from_feed_data(config, old_data);
+    if !old_path.exists() {
+        return Ok(());
+    }
+    let mut feed = read_or_create_feed(&old_path, gen, old_data)?;
+    feed.set_id(new_data.id.clone());
+    for link in feed.links.iter_mut().filter(|l| l.rel == "self") {
+        link.set_href(format!("{}.atom", new_data.id));
+    }
+    let new_path = path_from_feed_data(config, new_data);
+    feed.write_to(File::create(&new_path)?)?;
+    std::fs::set_permissions(&new_path, Permissions::from_mode(0o644))?;
+    std::fs::remove_file(old_path)?;
+    Ok(())
+}
+
+fn do_reset(config: &Config, db_path: &Path, key: &str) -> Result<(), Box<dyn Error>> {
+    let db = db::open(db_path)?;
+    let feed_data = get_feed_data(&db, key)?;
+    db.open_tree(format!("delivered_{key}"))?.clear()?;
+    db.open_tree("replay_state")?.remove(key)?;
+    // A sped-up replay restarts now, from the first entry, and an anniversary replay from the
+    // entries due today.
+    let schedule = match get_schedule(&db, key)? {
+        Some(Schedule::Scaled { factor, .. }) => next_published(&db, key)?
+            .map(|origin| Schedule::Scaled { factor, start: Utc::now(), origin }),
+        Some(Schedule::Anniversary { years, .. }) => {
+            Some(Schedule::Anniversary { years, start: Utc::now() })
+        }
+        _ => None,
+    };
+    if let Some(schedule) = schedule {
+        db.open_tree("replay_schedules")?.insert(key, db::encode(&schedule)?)?;
+    }
+    remove_feed_files(config, &feed_data)?;
+    println!("Reset {key}; the next generate run starts its replay from the beginning");
+    Ok(())
+}
+
+fn do_migrate(db_path: &Path, dry_run: bool) -> Result<(), Box<dyn Error>> {
+    if dry_run {
+        return Ok(db::dry_run(db_path)?);
+    }
+    let db = db::open(db_path)?;
+    println!("Schema version {}", db::schema_version(&db)?);
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn Error>> {
     let matches = clap_app!((PROG_NAME) =>
         (version: VERSION)
@@ -124,10 +753,65 @@ fn main() -> Result<(), Box<dyn Error>> {
         (@subcommand scrape =>
             (about: "loads a blog's archive into the local DB for later replay")
             (@arg URL: +required "URL of the blog to scrape")
+            (@arg TYPE: -t --type +takes_value
+                "Skip detection and scrape as the given blog type: blogger, wordpress, ghost, \
+                substack, tumblr, feed, or wayback (slow; rebuilds the blog from Internet Archive \
+                captures)")
+            (@arg STRICT: --strict "Refuse to store the archive if any posts appear to be missing")
+            (@arg COMMENTS: --comments "Also scrape each post's comments (Blogger and WordPress only)")
+        )
+        (@subcommand import_wxr =>
+            (name: "import-wxr")
+            (about: "loads a WordPress export (WXR) file into the local DB for later replay")
+            (@arg FILE: +required "Path to the WXR file to import")
+        )
+        (@subcommand import_blogger =>
+            (name: "import-blogger")
+            (about: "loads a Blogger backup or Google Takeout file into the local DB for later replay")
+            (@arg FILE: +required "Path to the Atom archive file to import")
+        )
+        (@subcommand import_dir =>
+            (name: "import-dir")
+            (about: "loads a directory of Markdown posts into the local DB for later replay")
+            (@arg PATH: +required "Path to the directory of Markdown files")
+            (@arg BASE_URL: --("base-url") +takes_value
+                "URL the posts are published under; defaults to the site config's base URL")
+            (@arg TITLE: --title +takes_value
+                "Title of the blog; defaults to the site config's title")
         )
         (@subcommand generate =>
             (about: "generates a feed for each blog in the local DB")
         )
+        (@subcommand schedule =>
+            (about: "shows or sets how often a blog releases entries")
+            (@arg KEY: +required "Key of the blog, as shown by ls")
+            (@arg SPEC: ...
+                "How many entries to release when, such as \"one per day\", \"every 12 hours\" \
+                or \"3 posts every Monday\"; \"10x\" keeps the original gaps between entries, 10 \
+                times shorter; \"none\" goes back to one per generate run. Releases missed \
+                while generate wasn't running all happen on its next run")
+        )
+        (@subcommand status =>
+            (about: "shows how far each blog's replay has got")
+            (@arg BLOGS: ... "Limit to the given blog key(s)")
+        )
+        (@subcommand rm =>
+            (about: "removes a blog and its generated feeds")
+            (@arg KEY: +required "Key of the blog, as shown by ls")
+        )
+        (@subcommand rename =>
+            (about: "changes a blog's key, moving its generated feeds to match")
+            (@arg OLD: +required "Current key of the blog")
+            (@arg NEW: +required "New key for the blog")
+        )
+        (@subcommand reset =>
+            (about: "restarts a blog's replay from its first entry")
+            (@arg KEY: +required "Key of the blog, as shown by ls")
+        )
+        (@subcommand migrate =>
+            (about: "upgrades the local DB to the current schema; other commands do this too")
+            (@arg DRY_RUN: --("dry-run") "Only show what would change")
+        )
         (@subcommand ls =>
             (about: "lists blog metadata from the local DB")
             (@arg LONG: -l --long "Also show cached post titles")
@@ -151,12 +835,68 @@ fn main() -> Result<(), Box<dyn Error>> {
             let url_arg = sub_match.value_of("URL");
             do_scrape(
                 url_arg.ok_or("missing URL arg")?,
+                sub_match.value_of("TYPE").map(str::parse).transpose()?,
+                sub_match.is_present("STRICT"),
+                sub_match.is_present("COMMENTS"),
+                &config,
+                &generator,
+                &db_path,
+            )
+        }
+        ("import-wxr", Some(sub_match)) => {
+            let file_arg = sub_match.value_of("FILE");
+            do_import_wxr(
+                Path::new(file_arg.ok_or("missing FILE arg")?),
+                &config,
+                &generator,
+                &db_path,
+            )
+        }
+        ("import-blogger", Some(sub_match)) => {
+            let file_arg = sub_match.value_of("FILE");
+            do_import_blogger(
+                Path::new(file_arg.ok_or("missing FILE arg")?),
+                &config,
+                &generator,
+                &db_path,
+            )
+        }
+        ("import-dir", Some(sub_match)) => {
+            let path_arg = sub_match.value_of("PATH");
+            do_import_dir(
+                Path::new(path_arg.ok_or("missing PATH arg")?),
+                sub_match.value_of("BASE_URL"),
+                sub_match.value_of("TITLE"),
                 &config,
                 &generator,
                 &db_path,
             )
         }
         ("generate", Some(_)) => do_generate(&config, &generator, &db_path),
+        ("schedule", Some(sub_match)) => {
+            let key_arg = sub_match.value_of("KEY");
+            let spec = sub_match.values_of("SPEC").map(|s| s.collect::<Vec<_>>().join(" "));
+            do_schedule(&db_path, key_arg.ok_or("missing KEY arg")?, spec.as_deref())
+        }
+        ("status", Some(sub_match)) => {
+            let blogs = sub_match.values_of("BLOGS")
+                .map_or_else(HashSet::new, |b| b.collect());
+            do_status(&db_path, &blogs)
+        }
+        ("rm", Some(sub_match)) => {
+            do_rm(&config, &db_path, sub_match.value_of("KEY").ok_or("missing KEY arg")?)
+        }
+        ("rename", Some(sub_match)) => do_rename(
+            &config,
+            &generator,
+            &db_path,
+            sub_match.value_of("OLD").ok_or("missing OLD arg")?,
+            sub_match.value_of("NEW").ok_or("missing NEW arg")?,
+        ),
+        ("reset", Some(sub_match)) => {
+            do_reset(&config, &db_path, sub_match.value_of("KEY").ok_or("missing KEY arg")?)
+        }
+        ("migrate", Some(sub_match)) => do_migrate(&db_path, sub_match.is_present("DRY_RUN")),
         ("ls", Some(sub_match)) => {
             let blogs = sub_match.values_of("BLOGS")
                 .map_or_else(HashSet::new, |b| b.collect());
@@ -165,3 +905,239 @@ fn main() -> Result<(), Box<dyn Error>> {
         _ => Ok(()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use atom_syndication::EntryBuilder;
+
+    fn test_entry(n: u32) -> Entry {
+        let published = parse_datetime(&format!("2010-0{n}-01T00:00:00+00:00"));
+        EntryBuilder::default()
+            .title(format!("Post {n}"))
+            .id(format!("https://feeds.example/blog/{n}"))
+            .published(published)
+            .updated(published.unwrap())
+            .build()
+    }
+
+    #[test]
+    fn merge_keeps_entries_rotated_out_of_the_feed_delivered() {
+        let dir = std::env::temp_dir().join(format!("blog-replay-merge-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Config {
+            feed_path: dir.to_string_lossy().into_owned(),
+            max_entries: Some(1),
+            ..Default::default()
+        };
+        let gen = Generator::default();
+        let feed_data = FeedData {
+            id: "https://feeds.example/blog".to_string(),
+            key: "blog".to_string(),
+            title: "Blog".to_string(),
+            url: "https://blog.example/".to_string(),
+        };
+        // An older version released posts 1 to 3 and deleted them from the DB, and only post 3
+        // is left in the feed.
+        let mut released = test_entry(3);
+        released.set_updated(Utc::now());
+        write_feed_entries(&config, &feed_data, &gen, vec![released]).unwrap();
+
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        let entries: Vec<Entry> = (1..=4).map(test_entry).collect();
+        let merged = merge_entries(&config, &feed_data, &gen, &db, &entries).unwrap();
+        let new: Vec<&str> = merged.new.iter().map(|e| e.title.value.as_str()).collect();
+        assert_eq!(new, ["Post 4"]);
+        assert_eq!(db.open_tree("delivered_blog").unwrap().len(), 3);
+
+        let released = generate_feed(&config, &feed_data, &gen, &db, Release::Count(1), Utc::now());
+        let feed = read_or_create_feed(path_from_feed_data(&config, &feed_data), &gen, &feed_data);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(released.unwrap(), 1);
+        let feed = feed.unwrap();
+        let titles: Vec<&str> = feed.entries.iter().map(|e| e.title.value.as_str()).collect();
+        assert_eq!(titles, ["Post 4"]);
+    }
+
+    #[test]
+    fn generate_skips_entries_before_a_release_window() {
+        let dir = std::env::temp_dir().join(format!("blog-replay-window-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = Config { feed_path: dir.to_string_lossy().into_owned(), ..Default::default() };
+        let gen = Generator::default();
+        let blog = TestBlog { entries: (1..=4).map(test_entry).collect() };
+        let feed_data = blog.feed_data();
+        let db = sled::Config::new().temporary(true).open().unwrap();
+        merge_entries(&config, &feed_data, &gen, &db, &blog.entries).unwrap();
+
+        let window = Release::PublishedBetween(
+            parse_datetime("2010-02-15T00:00:00+00:00").unwrap(),
+            parse_datetime("2010-03-15T00:00:00+00:00").unwrap(),
+        );
+        let released = generate_feed(&config, &feed_data, &gen, &db, window, Utc::now());
+        let feed = read_or_create_feed(path_from_feed_data(&config, &feed_data), &gen, &feed_data);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(released.unwrap(), 1);
+        let feed = feed.unwrap();
+        let titles: Vec<&str> = feed.entries.iter().map(|e| e.title.value.as_str()).collect();
+        assert_eq!(titles, ["Post 3"]);
+    }
+
+    #[test]
+    fn backdated_posts_found_by_a_later_scrape_are_replayed() {
+        let dir = std::env::temp_dir().join(format!("blog-replay-backdate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("sled_db");
+        let config = Config { feed_path: dir.to_string_lossy().into_owned(), ..Default::default() };
+        let gen = Generator::default();
+
+        let blog = TestBlog { entries: (1..=3).map(test_entry).collect() };
+        store_blog(&blog, false, false, &config, &gen, &db_path).unwrap();
+        // Post 1 has been released, and a post dated before it shows up later.
+        let published = parse_datetime("2009-12-01T00:00:00+00:00");
+        let backdated = EntryBuilder::default()
+            .title("Backdated")
+            .id("https://feeds.example/blog/0")
+            .published(published)
+            .updated(published.unwrap())
+            .build();
+        let entries: Vec<Entry> =
+            std::iter::once(backdated).chain((1..=3).map(test_entry)).collect();
+        let db = db::open(&db_path).unwrap();
+        let feed_data = blog.feed_data();
+        let merged = merge_entries(&config, &feed_data, &gen, &db, &entries).unwrap();
+        let new: Vec<&str> = merged.new.iter().map(|e| e.title.value.as_str()).collect();
+        let released = generate_feed(&config, &feed_data, &gen, &db, Release::Count(1), Utc::now());
+        let feed = read_or_create_feed(path_from_feed_data(&config, &feed_data), &gen, &feed_data);
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(new, ["Backdated"]);
+        assert_eq!(released.unwrap(), 1);
+        let feed = feed.unwrap();
+        let mut titles: Vec<&str> = feed.entries.iter().map(|e| e.title.value.as_str()).collect();
+        titles.sort();
+        assert_eq!(titles, ["Backdated", "Post 1"]);
+    }
+
+    struct TestBlog {
+        entries: Vec<Entry>,
+    }
+
+    impl Blog for TestBlog {
+        fn blog_type(&self) -> BlogType {
+            BlogType::Markdown
+        }
+
+        fn feed_data(&self) -> FeedData {
+            FeedData {
+                id: "https://feeds.example/my_blog".to_string(),
+                key: "my_blog".to_string(),
+                title: "My Blog".to_string(),
+                url: "https://blog.example/".to_string(),
+            }
+        }
+
+        fn entries(&self) -> anyhow::Result<Vec<Entry>> {
+            Ok(self.entries.clone())
+        }
+    }
+
+    #[test]
+    fn rescrape_after_rename_updates_the_renamed_blog() {
+        let dir = std::env::temp_dir().join(format!("blog-replay-rename-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("sled_db");
+        let config = Config {
+            feed_path: dir.to_string_lossy().into_owned(),
+            feed_url_base: "https://feeds.example".to_string(),
+            ..Default::default()
+        };
+        let gen = Generator::default();
+
+        let blog = TestBlog { entries: (1..=2).map(test_entry).collect() };
+        store_blog(&blog, false, false, &config, &gen, &db_path).unwrap();
+        do_rename(&config, &gen, &db_path, "my_blog", "renamed").unwrap();
+        let blog = TestBlog { entries: (1..=3).map(test_entry).collect() };
+        store_blog(&blog, false, false, &config, &gen, &db_path).unwrap();
+
+        let db = db::open(&db_path).unwrap();
+        let keys: Vec<_> = db.open_tree("feed_metadata").unwrap().iter().keys().collect();
+        let renamed = get_feed_data(&db, "renamed").unwrap();
+        let entries = db.open_tree("entries_renamed").unwrap().len();
+        let delivered = db.open_tree("delivered_renamed").unwrap().len();
+        let feed_moved = !dir.join("my_blog.atom").exists() && dir.join("renamed.atom").exists();
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(keys.len(), 1);
+        assert_eq!(renamed.id, "https://feeds.example/renamed");
+        assert_eq!((entries, delivered), (3, 1));
+        assert!(feed_moved);
+    }
+
+    #[test]
+    fn rm_removes_trees_and_aliases() {
+        let dir = std::env::temp_dir().join(format!("blog-replay-rm-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("sled_db");
+        let config = Config { feed_path: dir.to_string_lossy().into_owned(), ..Default::default() };
+        let gen = Generator::default();
+
+        let blog = TestBlog { entries: (1..=2).map(test_entry).collect() };
+        store_blog(&blog, false, false, &config, &gen, &db_path).unwrap();
+        do_rename(&config, &gen, &db_path, "my_blog", "renamed").unwrap();
+        do_schedule(&db_path, "renamed", Some("daily")).unwrap();
+        do_rm(&config, &db_path, "renamed").unwrap();
+
+        let db = db::open(&db_path).unwrap();
+        let trees: Vec<String> =
+            db.tree_names().iter().map(|n| String::from_utf8_lossy(n).into_owned()).collect();
+        let leftovers: Vec<usize> =
+            ["feed_metadata", "replay_schedules", "replay_state", "renamed_blogs"]
+                .iter()
+                .map(|t| db.open_tree(t).unwrap().len())
+                .collect();
+        let feed_removed = !dir.join("renamed.atom").exists();
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(!trees.iter().any(|t| t.ends_with("_renamed") || t.ends_with("_my_blog")));
+        assert_eq!(leftovers, [0, 0, 0, 0]);
+        assert!(feed_removed);
+    }
+
+    #[test]
+    fn reset_restarts_a_scaled_schedule_from_the_first_entry() {
+        let dir = std::env::temp_dir().join(format!("blog-replay-reset-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let db_path = dir.join("sled_db");
+        let config = Config { feed_path: dir.to_string_lossy().into_owned(), ..Default::default() };
+        let gen = Generator::default();
+
+        let blog = TestBlog { entries: (1..=3).map(test_entry).collect() };
+        store_blog(&blog, false, false, &config, &gen, &db_path).unwrap();
+        do_schedule(&db_path, "my_blog", Some("10x")).unwrap();
+        let before_reset = Utc::now();
+        do_reset(&config, &db_path, "my_blog").unwrap();
+
+        let db = db::open(&db_path).unwrap();
+        let schedule = get_schedule(&db, "my_blog").unwrap();
+        let delivered = db.open_tree("delivered_my_blog").unwrap().len();
+        let last_release = get_last_release(&db, "my_blog").unwrap();
+        let feed_removed = !dir.join("my_blog.atom").exists();
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let Some(Schedule::Scaled { factor, start, origin }) = schedule else {
+            panic!("the schedule should still be scaled");
+        };
+        assert_eq!(factor, 10.0);
+        assert!(start >= before_reset);
+        assert_eq!(Some(origin), test_entry(1).published);
+        assert_eq!(delivered, 0);
+        assert!(last_release.is_none());
+        assert!(feed_removed);
+    }
+}
@@ -0,0 +1,367 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use atom_syndication::{
+    CategoryBuilder, ContentBuilder, Entry, EntryBuilder, LinkBuilder, Person,
+};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Offset, Utc};
+use lazy_static::lazy_static;
+use pulldown_cmark::{html, Options, Parser};
+use regex::Regex;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::common::*;
+
+static MARKDOWN_EXTENSIONS: &[&str] = &["md", "markdown"];
+
+// Where Hugo and Zola ("content") and Jekyll ("_posts") keep posts, relative to the site root. The
+// rest of a site holds templates and documentation rather than posts.
+static CONTENT_DIRS: &[&str] = &["content", "_posts"];
+
+// Dates may be TOML datetimes or any of the string formats used by static site generators.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum DateField {
+    Toml(toml::value::Datetime),
+    Text(String),
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            OneOrMany::One(s) => vec![s],
+            OneOrMany::Many(v) => v,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct FrontMatter {
+    title: Option<String>,
+    date: Option<DateField>,
+    author: Option<OneOrMany>,
+    authors: Option<OneOrMany>,
+    tags: Option<OneOrMany>,
+    categories: Option<OneOrMany>,
+    slug: Option<String>,
+    permalink: Option<String>,
+    #[serde(default)]
+    draft: bool,
+}
+
+// The subset of Hugo, Zola and Jekyll site configuration that we can use as defaults.
+#[derive(Deserialize, Debug, Default)]
+struct SiteConfig {
+    title: Option<String>,
+    #[serde(alias = "baseURL", alias = "base_url", alias = "url")]
+    base_url: Option<String>,
+}
+
+// Returns None if the directory isn't the root of a site.
+fn read_site_config(dir: &Path) -> Option<SiteConfig> {
+    for name in ["hugo.toml", "config.toml"] {
+        if let Ok(s) = fs::read_to_string(dir.join(name)) {
+            return Some(toml::from_str(&s).unwrap_or_default());
+        }
+    }
+    fs::read_to_string(dir.join("_config.yml"))
+        .ok()
+        .map(|s| serde_yaml::from_str(&s).unwrap_or_default())
+}
+
+pub fn load_blog<'a>(
+    config: &'a Config,
+    dir: &Path,
+    base_url: Option<&str>,
+    title: Option<&str>,
+) -> anyhow::Result<Box<dyn Blog + 'a>> {
+    if !dir.is_dir() {
+        anyhow::bail!("{} is not a directory", dir.display());
+    }
+    let site_config = read_site_config(dir);
+    // At a site's root, only look for posts where the generator keeps them.
+    let content_dirs: Vec<PathBuf> = match &site_config {
+        Some(_) => CONTENT_DIRS.iter().map(|d| dir.join(d)).filter(|d| d.is_dir()).collect(),
+        None => vec![dir.to_path_buf()],
+    };
+    if content_dirs.is_empty() {
+        anyhow::bail!("Found a site config in {}, but no content directory", dir.display());
+    }
+    let site_config = site_config.unwrap_or_default();
+    let base_url = base_url
+        .map(str::to_string)
+        .or(site_config.base_url)
+        .ok_or_else(|| anyhow::anyhow!("No base URL given, and none found in the site config"))?;
+    // Make sure relative links are resolved inside the base URL rather than replacing its path.
+    let base_url = Url::parse(&format!("{}/", base_url.trim_end_matches('/')))?;
+    let title = title
+        .map(str::to_string)
+        .or(site_config.title)
+        .or_else(|| dir.canonicalize().ok()?.file_name()?.to_str().map(str::to_string))
+        .ok_or_else(|| anyhow::anyhow!("No title given, and none found in the site config"))?;
+
+    let key = sanitize_blog_key(&title);
+    let feed_id = format!("{}/{}", config.feed_url_base, key);
+
+    Ok(Box::new(MarkdownBlog {
+        dir: dir.to_path_buf(),
+        content_dirs,
+        base_url,
+        title,
+        key,
+        feed_id,
+    }))
+}
+
+fn find_markdown_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for dir_entry in fs::read_dir(dir)? {
+        let path = dir_entry?.path();
+        let hidden = path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            find_markdown_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| MARKDOWN_EXTENSIONS.contains(&e))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// Splits a document into its YAML ("---") or TOML ("+++") front matter and its body. Returns None
+// for documents without front matter, which aren't posts.
+fn split_front_matter(text: &str) -> anyhow::Result<Option<(FrontMatter, &str)>> {
+    for (fence, is_toml) in [("---", false), ("+++", true)] {
+        let Some(rest) = text.strip_prefix(fence) else { continue };
+        let Some(end) = rest.find(&format!("\n{fence}")) else { continue };
+        let front = &rest[..end];
+        let body = rest[end + 1 + fence.len()..].trim_start_matches(['\r', '\n']);
+        let front_matter = if is_toml {
+            toml::from_str(front)?
+        } else if front.trim().is_empty() {
+            FrontMatter::default()
+        } else {
+            serde_yaml::from_str(front)?
+        };
+        return Ok(Some((front_matter, body)));
+    }
+    Ok(None)
+}
+
+fn parse_front_matter_date(date: &DateField) -> Option<DateTime<FixedOffset>> {
+    let s = match date {
+        DateField::Toml(d) => d.to_string(),
+        DateField::Text(s) => s.clone(),
+    };
+    let s = s.trim();
+    let assume_utc = |d: NaiveDateTime| DateTime::<Utc>::from_utc(d, Utc).with_timezone(&Utc.fix());
+    parse_datetime(s)
+        .or_else(|| {
+            DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S %z")
+                .ok()
+                .map(|d| d.with_timezone(&Utc.fix()))
+        })
+        .or_else(|| parse_assuming_utc(s))
+        .or_else(|| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").ok().map(assume_utc))
+        .or_else(|| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").ok().map(assume_utc))
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .map(|d| assume_utc(d.and_hms(0, 0, 0)))
+        })
+}
+
+// Jekyll names posts like "2020-01-31-my-post.md"; returns the date and the remaining slug.
+fn split_filename_date(stem: &str) -> (Option<DateTime<FixedOffset>>, &str) {
+    lazy_static! {
+        static ref DATED: Regex = Regex::new(r"^(\d{4}-\d{2}-\d{2})-(.+)$").unwrap();
+    };
+    match DATED.captures(stem) {
+        Some(c) => (
+            parse_front_matter_date(&DateField::Text(c[1].to_string())),
+            c.get(2).map_or(stem, |m| m.as_str()),
+        ),
+        None => (None, stem),
+    }
+}
+
+fn render_markdown(body: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, Parser::new_ext(body, options));
+    rendered
+}
+
+struct MarkdownBlog {
+    dir: PathBuf,
+    content_dirs: Vec<PathBuf>,
+    base_url: Url,
+    title: String,
+    key: String,
+    feed_id: String,
+}
+
+impl Blog for MarkdownBlog {
+    fn blog_type(&self) -> BlogType {
+        BlogType::Markdown
+    }
+
+    fn feed_data(&self) -> FeedData {
+        FeedData {
+            id: self.feed_id.clone(),
+            key: self.key.clone(),
+            title: self.title.clone(),
+            url: self.base_url.to_string(),
+        }
+    }
+
+    fn entries(&self) -> anyhow::Result<Vec<Entry>> {
+        let mut files = Vec::new();
+        for content_dir in &self.content_dirs {
+            let mut found = Vec::new();
+            find_markdown_files(content_dir, &mut found)?;
+            files.extend(found.into_iter().map(|f| (content_dir, f)));
+        }
+        println!(r#"Importing "{}" ({} files)"#, &self.title, files.len());
+
+        let mut posts = Vec::new();
+        for (content_dir, file) in &files {
+            let text = fs::read_to_string(file)?;
+            let (front_matter, body) = match split_front_matter(&text) {
+                Ok(Some(split)) => split,
+                Ok(None) => continue,
+                Err(e) => {
+                    println!("WARNING: skipping {}: bad front matter: {e}", file.display());
+                    continue;
+                }
+            };
+            if let Some(entry) = self.file_to_entry(content_dir, file, front_matter, body) {
+                posts.push(entry);
+            }
+        }
+
+        Ok(posts)
+    }
+}
+
+impl MarkdownBlog {
+    fn file_to_entry(
+        &self,
+        content_dir: &Path,
+        file: &Path,
+        front_matter: FrontMatter,
+        body: &str,
+    ) -> Option<Entry> {
+        let stem = file.file_stem()?.to_str()?;
+        // Hugo and Zola use _index.md for section listings rather than posts.
+        if front_matter.draft || stem == "_index" {
+            return None;
+        }
+        let (filename_date, filename_slug) = split_filename_date(stem);
+        let published = front_matter
+            .date
+            .as_ref()
+            .and_then(parse_front_matter_date)
+            .or(filename_date);
+        let slug = front_matter.slug.as_deref().unwrap_or(filename_slug);
+        // Generators publish posts under their section, e.g. content/posts/a.md at /posts/a/.
+        let section = file.parent()?.strip_prefix(content_dir).ok()?.to_str()?;
+        let link = match &front_matter.permalink {
+            Some(p) => self.base_url.join(p.trim_start_matches('/')),
+            None if section.is_empty() => self.base_url.join(&format!("{slug}/")),
+            None => self.base_url.join(&format!("{section}/{slug}/")),
+        }
+        .ok()?;
+        let relative = file.strip_prefix(&self.dir).ok()?.with_extension("");
+        let content = ContentBuilder::default()
+            .value(render_markdown(body))
+            .content_type(Some("html".to_string()))
+            .build();
+
+        let authors: Vec<Person> = front_matter
+            .author
+            .or(front_matter.authors)
+            .map(OneOrMany::into_vec)
+            .unwrap_or_default()
+            .drain(..)
+            .map(|name| Person { name, email: None, uri: None })
+            .collect();
+        let categories: Vec<_> = front_matter
+            .tags
+            .into_iter()
+            .chain(front_matter.categories)
+            .flat_map(OneOrMany::into_vec)
+            .map(|t| CategoryBuilder::default().term(t).build())
+            .collect();
+
+        Some(EntryBuilder::default()
+            .title(front_matter.title.unwrap_or_else(|| slug.to_string()))
+            .id(format!("{}/{}", self.feed_id, relative.to_string_lossy()))
+            .published(published)
+            .authors(authors)
+            .categories(categories)
+            .content(content)
+            .link(
+                LinkBuilder::default()
+                    .href(link.to_string())
+                    .rel("alternate")
+                    .build(),
+            )
+            .build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(path: &Path, text: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+
+    #[test]
+    fn imports_only_posts_from_hugo_content() {
+        let dir = std::env::temp_dir().join(format!("blog-replay-hugo-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        write(&dir.join("config.toml"), "title = 'Hugo Site'\nbaseURL = 'https://h.example/'\n");
+        write(&dir.join("README.md"), "# How to build this site\n");
+        write(&dir.join("archetypes/default.md"), "+++\ndate = {{ .Date }}\n+++\n");
+        let first = "+++\ntitle = 'First'\ndate = 2020-01-02\n+++\nHi\n";
+        write(&dir.join("content/posts/first.md"), first);
+        write(&dir.join("content/posts/broken.md"), "+++\ndate = {{ .Date }}\n+++\n");
+        write(&dir.join("content/about.md"), "---\ntitle: About\n---\nMe\n");
+        write(&dir.join("content/notes.md"), "No front matter here\n");
+
+        let config = Config {
+            feed_url_base: "https://feeds.example".to_string(),
+            ..Default::default()
+        };
+        let blog = load_blog(&config, &dir, None, None).unwrap();
+        let mut entries = blog.entries().unwrap();
+        entries.sort_by(|a, b| a.title.value.cmp(&b.title.value));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let titles: Vec<_> = entries.iter().map(|e| e.title.value.as_str()).collect();
+        assert_eq!(titles, ["About", "First"]);
+        assert_eq!(entries[0].links[0].href, "https://h.example/about/");
+        assert_eq!(entries[1].links[0].href, "https://h.example/posts/first/");
+        assert_eq!(entries[1].id, "https://feeds.example/hugo_site/content/posts/first");
+    }
+}
@@ -0,0 +1,251 @@
+use atom_syndication::{
+    CategoryBuilder, ContentBuilder, Entry, EntryBuilder, LinkBuilder, Person, Text,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Url;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+
+use crate::common::*;
+
+// Number of posts requested per archive page; Substack caps this at a small value.
+static ARCHIVE_PAGE_SIZE: usize = 12;
+
+// Category added to posts whose full text is only available to subscribers.
+static PAYWALLED_TERM: &str = "paywalled";
+
+fn is_substack_markup(html: &str) -> bool {
+    lazy_static! {
+        static ref SUBSTACK_MARKUP: Regex =
+            Regex::new(r#"(?i)substackcdn\.com|<meta[^>]+content\s*=\s*["']Substack["']"#).unwrap();
+    };
+    SUBSTACK_MARKUP.is_match(html)
+}
+
+pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str)
+    -> anyhow::Result<Box<dyn Blog + 'a>>
+{
+    let site_url = Url::parse(url)?.join("/")?;
+    let homepage = retry_request(config, || {
+        Ok(client.get(site_url.clone()).send()?.error_for_status()?.text()?)
+    })?;
+    // Publications on custom domains can only be recognized by their markup.
+    let substack_host = site_url.host_str().is_some_and(|h| h.ends_with(".substack.com"));
+    if !substack_host && !is_substack_markup(&homepage) {
+        anyhow::bail!("{url} is not a Substack publication");
+    }
+
+    let title = meta_content(&homepage, "og:site_name")
+        .or_else(|| html_title(&homepage))
+        .ok_or_else(|| anyhow::anyhow!("Could not find the publication name of {url}"))?;
+    let key = sanitize_blog_key(&title);
+    let feed_id = format!("{}/{}", config.feed_url_base, key);
+
+    Ok(Box::new(SubstackBlog {
+        archive_api_url: site_url.join("api/v1/archive")?,
+        posts_api_url: site_url.join("api/v1/posts/")?,
+        site_url,
+        title,
+        key,
+        feed_id,
+        config,
+        client,
+    }))
+}
+
+struct SubstackBlog<'a> {
+    site_url: Url,
+    archive_api_url: Url,
+    posts_api_url: Url,
+    title: String,
+    key: String,
+    feed_id: String,
+    config: &'a Config,
+    client: &'a Client,
+}
+
+impl Blog for SubstackBlog<'_> {
+    fn blog_type(&self) -> BlogType {
+        BlogType::Substack
+    }
+
+    fn feed_data(&self) -> FeedData {
+        FeedData {
+            id: self.feed_id.clone(),
+            key: self.key.clone(),
+            title: self.title.clone(),
+            url: self.site_url.to_string(),
+        }
+    }
+
+    fn entries(&self) -> anyhow::Result<Vec<Entry>> {
+        let mut posts: Vec<Entry> = Vec::new();
+
+        // The archive endpoint doesn't report a total, so grow the progress bar as we go.
+        println!(r#"Scraping "{}""#, &self.title);
+        let pb = init_progress_bar(0);
+        let mut offset = 0;
+        loop {
+            let summaries = retry_request(self.config, || self.get_archive_once(offset))?;
+            if summaries.is_empty() {
+                break;
+            }
+            pb.inc_length(summaries.len().try_into().unwrap());
+            offset += summaries.len();
+            for summary in &summaries {
+                let post = retry_request(self.config, || self.get_post_once(&summary.slug))?;
+                posts.push(self.post_to_entry(&post));
+                pb.inc(1);
+
+                std::thread::sleep(std::time::Duration::from_millis(500));
+            }
+        }
+        pb.finish();
+
+        Ok(posts)
+    }
+}
+
+impl SubstackBlog<'_> {
+    fn get_archive_once(&self, offset: usize) -> anyhow::Result<Vec<ArchivePost>> {
+        let resp = self
+            .client
+            .get(self.archive_api_url.clone())
+            .query(&[
+                ("sort", "new"),
+                ("offset", &format!("{offset}")),
+                ("limit", &format!("{ARCHIVE_PAGE_SIZE}")),
+            ])
+            .send()?;
+
+        Ok(resp.error_for_status()?.json()?)
+    }
+
+    fn get_post_once(&self, slug: &str) -> anyhow::Result<Post> {
+        let resp = self.client.get(self.posts_api_url.join(slug)?).send()?;
+
+        Ok(resp.error_for_status()?.json()?)
+    }
+
+    fn post_to_entry(&self, post: &Post) -> Entry {
+        // "only_free" posts are for free subscribers, and may still come with their full text.
+        let paid = matches!(post.audience.as_str(), "only_paid" | "founding");
+        let paywalled = paid || post.body_html.is_none();
+        let body = if paywalled {
+            // Paywalled posts come with a preview at most, so say why the rest is missing.
+            let preview = post.body_html.clone()
+                .or_else(|| {
+                    post.truncated_body_text.as_ref().map(|t| format!("<p>{}</p>", escape_html(t)))
+                })
+                .unwrap_or_default();
+            format!(
+                "{preview}<p><em>This post is for {readers}. \
+                Read it at <a href=\"{url}\">{url}</a>.</em></p>",
+                readers = if paid { "paid subscribers" } else { "subscribers" },
+                url = escape_html(&post.canonical_url),
+            )
+        } else {
+            post.body_html.clone().unwrap_or_default()
+        };
+        let content = ContentBuilder::default()
+            .value(body)
+            .content_type(Some("html".to_string()))
+            .build();
+        let summary = post
+            .subtitle
+            .as_ref()
+            .filter(|s| !s.is_empty())
+            .map(|s| Text::plain(s.clone()));
+        let categories: Vec<_> = if paywalled {
+            vec![CategoryBuilder::default().term(PAYWALLED_TERM).build()]
+        } else {
+            Vec::new()
+        };
+
+        EntryBuilder::default()
+            .title(post.title.clone())
+            .id(format!("{}/{}", self.feed_id, post.id))
+            .published(parse_datetime(&post.post_date))
+            .authors(
+                post.published_bylines
+                    .iter()
+                    .map(|b| Person {
+                        name: b.name.clone(),
+                        email: None,
+                        uri: None,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .categories(categories)
+            .summary(summary)
+            .content(content)
+            .link(
+                LinkBuilder::default()
+                    .href(post.canonical_url.clone())
+                    .rel("alternate")
+                    .build(),
+            )
+            .build()
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct ArchivePost {
+    slug: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Byline {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Post {
+    id: u64,
+    title: String,
+    subtitle: Option<String>,
+    post_date: String,
+    canonical_url: String,
+    audience: String,
+    body_html: Option<String>,
+    truncated_body_text: Option<String>,
+    #[serde(rename = "publishedBylines", default)]
+    published_bylines: Vec<Byline>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_paywalled_preview_and_url() {
+        let config = Config::default();
+        let client = Client::new();
+        let site_url = Url::parse("https://s.example/").unwrap();
+        let blog = SubstackBlog {
+            archive_api_url: site_url.join("api/v1/archive").unwrap(),
+            posts_api_url: site_url.join("api/v1/posts/").unwrap(),
+            site_url,
+            title: "S".to_string(),
+            key: "s".to_string(),
+            feed_id: "https://feeds.example/s".to_string(),
+            config: &config,
+            client: &client,
+        };
+        let post: Post = serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "title": "Paid",
+            "post_date": "2020-01-01T00:00:00.000Z",
+            "canonical_url": "https://s.example/p/a\"b",
+            "audience": "only_paid",
+            "body_html": null,
+            "truncated_body_text": "1 < 2 & \"so on\"",
+        }))
+        .unwrap();
+        let entry = blog.post_to_entry(&post);
+        let html = entry.content.unwrap().value.unwrap();
+        assert!(html.starts_with("<p>1 &lt; 2 &amp; &quot;so on&quot;</p>"), "{html}");
+        assert!(html.contains("<a href=\"https://s.example/p/a&quot;b\">"), "{html}");
+    }
+}
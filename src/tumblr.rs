@@ -0,0 +1,399 @@
+use atom_syndication::{CategoryBuilder, ContentBuilder, Entry, EntryBuilder, LinkBuilder};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Offset, Utc};
+use reqwest::Url;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+
+use crate::common::*;
+
+// Largest page sizes allowed by the v1 and v2 APIs respectively.
+static V1_PAGE_SIZE: usize = 50;
+static V2_PAGE_SIZE: usize = 20;
+
+// The v1 API returns JavaScript rather than JSON, wrapped in this assignment.
+static V1_PREFIX: &str = "var tumblr_api_read = ";
+
+pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str)
+    -> anyhow::Result<Box<dyn Blog + 'a>>
+{
+    let site_url = Url::parse(url)?.join("/")?;
+    let host = site_url
+        .host_str()
+        .ok_or_else(|| anyhow::anyhow!("URL has no host"))?
+        .to_string();
+
+    let api = if config.tumblr_api_key.is_empty() {
+        Api::V1(site_url.join("api/read/json")?)
+    } else {
+        Api::V2(Url::parse(&format!("https://api.tumblr.com/v2/blog/{host}/posts"))?)
+    };
+    let mut blog = TumblrBlog {
+        api,
+        site_url,
+        title: String::new(),
+        total: 0,
+        key: String::new(),
+        feed_id: String::new(),
+        config,
+        client,
+    };
+
+    // Fetch the first page just to read the blog's metadata.
+    let first_page = retry_request(config, || blog.get_page_once(0))?;
+    blog.title = first_page.title;
+    blog.total = first_page.total;
+    blog.key = sanitize_blog_key(&blog.title);
+    blog.feed_id = format!("{}/{}", config.feed_url_base, blog.key);
+
+    Ok(Box::new(blog))
+}
+
+enum Api {
+    // The public v1 API, which needs no key.
+    V1(Url),
+    // The v2 API, authenticated with Config::tumblr_api_key.
+    V2(Url),
+}
+
+struct TumblrBlog<'a> {
+    api: Api,
+    site_url: Url,
+    title: String,
+    total: usize,
+    key: String,
+    feed_id: String,
+    config: &'a Config,
+    client: &'a Client,
+}
+
+// A page of posts from either API version, normalized to a common shape.
+struct Page {
+    title: String,
+    total: usize,
+    posts: Vec<Post>,
+}
+
+struct Post {
+    id: String,
+    url: String,
+    timestamp: i64,
+    title: Option<String>,
+    body: String,
+    tags: Vec<String>,
+}
+
+impl Blog for TumblrBlog<'_> {
+    fn blog_type(&self) -> BlogType {
+        BlogType::Tumblr
+    }
+
+    fn feed_data(&self) -> FeedData {
+        FeedData {
+            id: self.feed_id.clone(),
+            key: self.key.clone(),
+            title: self.title.clone(),
+            url: self.site_url.to_string(),
+        }
+    }
+
+    fn entries(&self) -> anyhow::Result<Vec<Entry>> {
+        let mut posts: Vec<Entry> = Vec::new();
+
+        println!(r#"Scraping "{}" ({} posts)"#, &self.title, self.total);
+        let pb = init_progress_bar(self.total.try_into().unwrap());
+        let mut offset = 0;
+        while offset < self.total {
+            let page = retry_request(self.config, || self.get_page_once(offset))?;
+            if page.posts.is_empty() {
+                break;
+            }
+            pb.inc(page.posts.len().try_into().unwrap());
+            offset += page.posts.len();
+            posts.extend(page.posts.iter().map(|p| self.post_to_entry(p)));
+
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+        pb.finish();
+
+        Ok(posts)
+    }
+}
+
+impl TumblrBlog<'_> {
+    fn get_page_once(&self, offset: usize) -> anyhow::Result<Page> {
+        match &self.api {
+            Api::V1(api_url) => {
+                let text = self
+                    .client
+                    .get(api_url.clone())
+                    .query(&[("start", offset), ("num", V1_PAGE_SIZE)])
+                    .send()?
+                    .error_for_status()?
+                    .text()?;
+                let json = text
+                    .trim()
+                    .strip_prefix(V1_PREFIX)
+                    .ok_or_else(|| anyhow::anyhow!("Not a Tumblr v1 API response"))?
+                    .trim_end_matches(';');
+                let resp: V1Response = serde_json::from_str(json)?;
+                Ok(Page {
+                    title: resp.tumblelog.title,
+                    total: resp.posts_total,
+                    posts: resp.posts.into_iter().map(V1Post::normalize).collect(),
+                })
+            }
+            Api::V2(api_url) => {
+                let resp: V2Response = self
+                    .client
+                    .get(api_url.clone())
+                    .query(&[
+                        ("api_key", self.config.tumblr_api_key.as_str()),
+                        ("offset", &format!("{offset}")),
+                        ("limit", &format!("{V2_PAGE_SIZE}")),
+                    ])
+                    .send()?
+                    .error_for_status()?
+                    .json()?;
+                let resp = resp.response;
+                Ok(Page {
+                    title: resp.blog.title,
+                    total: resp.total_posts,
+                    posts: resp.posts.into_iter().map(V2Post::normalize).collect(),
+                })
+            }
+        }
+    }
+
+    fn post_to_entry(&self, post: &Post) -> Entry {
+        let content = ContentBuilder::default()
+            .value(post.body.clone())
+            .content_type(Some("html".to_string()))
+            .build();
+        let published: Option<DateTime<FixedOffset>> =
+            NaiveDateTime::from_timestamp_opt(post.timestamp, 0)
+                .map(|d| DateTime::<Utc>::from_utc(d, Utc).with_timezone(&Utc.fix()));
+        // Most Tumblr posts are untitled, but feed readers need something to show.
+        let title = post.title.clone().filter(|t| !t.is_empty()).unwrap_or_else(|| {
+            published.map_or_else(|| self.title.clone(), |p| p.format("%B %-d, %Y").to_string())
+        });
+
+        EntryBuilder::default()
+            .title(title)
+            .id(format!("{}/{}", self.feed_id, post.id))
+            .published(published)
+            .categories(
+                post.tags
+                    .iter()
+                    .map(|t| CategoryBuilder::default().term(t.clone()).build())
+                    .collect::<Vec<_>>(),
+            )
+            .content(content)
+            .link(
+                LinkBuilder::default()
+                    .href(post.url.clone())
+                    .rel("alternate")
+                    .build(),
+            )
+            .build()
+    }
+}
+
+// Captions, quotes and descriptions are already HTML, but URLs are not.
+fn photo_html(url: &str, caption: &str) -> String {
+    format!("<p><img src=\"{}\"/></p>{caption}", escape_html(url))
+}
+
+fn quote_html(text: &str, source: &str) -> String {
+    format!("<blockquote>{text}</blockquote><p>— {source}</p>")
+}
+
+fn link_html(url: &str, text: &str, description: &str) -> String {
+    format!("<p><a href=\"{}\">{text}</a></p>{description}", escape_html(url))
+}
+
+#[derive(Deserialize, Debug)]
+struct V1Tumblelog {
+    title: String,
+}
+
+// The v1 API names every field after the post type that uses it.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "kebab-case", default)]
+struct V1Post {
+    id: serde_json::Value,
+    url_with_slug: String,
+    #[serde(rename = "type")]
+    post_type: String,
+    unix_timestamp: i64,
+    regular_title: Option<String>,
+    regular_body: Option<String>,
+    photo_caption: Option<String>,
+    #[serde(rename = "photo-url-1280")]
+    photo_url: Option<String>,
+    quote_text: Option<String>,
+    quote_source: Option<String>,
+    link_text: Option<String>,
+    link_url: Option<String>,
+    link_description: Option<String>,
+    video_caption: Option<String>,
+    audio_caption: Option<String>,
+    tags: Vec<String>,
+}
+
+impl V1Post {
+    fn normalize(self) -> Post {
+        let s = |v: &Option<String>| v.clone().unwrap_or_default();
+        let (title, body) = match self.post_type.as_str() {
+            "regular" => (self.regular_title.clone(), s(&self.regular_body)),
+            "photo" => (None, photo_html(&s(&self.photo_url), &s(&self.photo_caption))),
+            "quote" => (None, quote_html(&s(&self.quote_text), &s(&self.quote_source))),
+            "link" => (
+                self.link_text.clone(),
+                link_html(
+                    &s(&self.link_url),
+                    &self.link_text.clone().unwrap_or_else(|| s(&self.link_url)),
+                    &s(&self.link_description),
+                ),
+            ),
+            _ => (None, s(&self.video_caption.clone().or_else(|| self.audio_caption.clone()))),
+        };
+        Post {
+            id: match &self.id {
+                serde_json::Value::String(s) => s.clone(),
+                id => id.to_string(),
+            },
+            url: self.url_with_slug,
+            timestamp: self.unix_timestamp,
+            title,
+            body,
+            tags: self.tags,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct V1Response {
+    tumblelog: V1Tumblelog,
+    #[serde(rename = "posts-total")]
+    posts_total: usize,
+    posts: Vec<V1Post>,
+}
+
+#[derive(Deserialize, Debug)]
+struct V2Blog {
+    title: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct V2PhotoSize {
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct V2Photo {
+    original_size: V2PhotoSize,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct V2Post {
+    id_string: String,
+    post_url: String,
+    #[serde(rename = "type")]
+    post_type: String,
+    timestamp: i64,
+    title: Option<String>,
+    body: Option<String>,
+    caption: Option<String>,
+    photos: Vec<V2Photo>,
+    text: Option<String>,
+    source: Option<String>,
+    url: Option<String>,
+    description: Option<String>,
+    tags: Vec<String>,
+}
+
+impl V2Post {
+    fn normalize(self) -> Post {
+        let s = |v: &Option<String>| v.clone().unwrap_or_default();
+        let (title, body) = match self.post_type.as_str() {
+            "text" => (self.title.clone(), s(&self.body)),
+            "photo" => (
+                None,
+                self.photos
+                    .iter()
+                    .map(|p| photo_html(&p.original_size.url, ""))
+                    .chain(std::iter::once(s(&self.caption)))
+                    .collect(),
+            ),
+            "quote" => (None, quote_html(&s(&self.text), &s(&self.source))),
+            "link" => (
+                self.title.clone(),
+                // Unlike v1's link text, v2's link titles are plain text.
+                link_html(
+                    &s(&self.url),
+                    &escape_html(self.title.as_ref().unwrap_or(&s(&self.url))),
+                    &s(&self.description),
+                ),
+            ),
+            _ => (self.title.clone(), s(&self.body.clone().or_else(|| self.caption.clone()))),
+        };
+        Post {
+            id: self.id_string,
+            url: self.post_url,
+            timestamp: self.timestamp,
+            title,
+            body,
+            tags: self.tags,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct V2Posts {
+    blog: V2Blog,
+    total_posts: usize,
+    posts: Vec<V2Post>,
+}
+
+#[derive(Deserialize, Debug)]
+struct V2Response {
+    response: V2Posts,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v2_post(json: serde_json::Value) -> Post {
+        serde_json::from_value::<V2Post>(json).unwrap().normalize()
+    }
+
+    #[test]
+    fn escapes_v2_link_titles_and_urls() {
+        let post = v2_post(serde_json::json!({
+            "type": "link",
+            "title": "a < b & \"c\"",
+            "url": "https://l.example/?q=\"x\"",
+            "description": "<p>Desc</p>",
+        }));
+        assert_eq!(
+            post.body,
+            "<p><a href=\"https://l.example/?q=&quot;x&quot;\">a &lt; b &amp; &quot;c&quot;</a></p>\
+            <p>Desc</p>"
+        );
+    }
+
+    #[test]
+    fn escapes_photo_urls() {
+        let post = v2_post(serde_json::json!({
+            "type": "photo",
+            "photos": [{"original_size": {"url": "https://p.example/a.jpg\" onerror=\"x"}}],
+            "caption": "<p>Cap</p>",
+        }));
+        assert_eq!(
+            post.body,
+            "<p><img src=\"https://p.example/a.jpg&quot; onerror=&quot;x\"/></p><p>Cap</p>"
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+
+use atom_syndication::{ContentBuilder, Entry, EntryBuilder, LinkBuilder};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, Offset, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Url;
+use reqwest::blocking::Client;
+
+use crate::common::*;
+use crate::feed::FeedDoc;
+
+// A single capture listed by the CDX API.
+struct Snapshot {
+    timestamp: String,
+    original: String,
+}
+
+impl Snapshot {
+    // The raw archived document, without the Wayback Machine's toolbar and rewritten links.
+    fn raw_url(&self, config: &Config) -> String {
+        format!("{}/web/{}id_/{}", config.wayback_url, self.timestamp, self.original)
+    }
+
+    fn captured(&self) -> Option<DateTime<FixedOffset>> {
+        NaiveDateTime::parse_from_str(&self.timestamp, "%Y%m%d%H%M%S")
+            .ok()
+            .map(|d| DateTime::<Utc>::from_utc(d, Utc).with_timezone(&Utc.fix()))
+    }
+}
+
+// Lists captures of everything under `url_prefix`, which is a host and path without a trailing
+// slash.
+fn query_cdx(config: &Config, client: &Client, url_prefix: &str, filter: &str, collapse: &str)
+    -> anyhow::Result<Vec<Snapshot>>
+{
+    let cdx_url = Url::parse(&format!("{}/cdx/search/cdx", config.wayback_url))?;
+    let mut rows: Vec<Vec<String>> = retry_request(config, || {
+        Ok(client
+                .get(cdx_url.clone())
+                .query(&[
+                    ("url", &format!("{url_prefix}/*")),
+                    ("output", &String::from("json")),
+                    ("fl", &String::from("timestamp,original")),
+                    ("filter", &String::from("statuscode:200")),
+                    ("filter", &format!("mimetype:{filter}")),
+                    ("collapse", &String::from(collapse)),
+                ])
+                .send()?
+                .error_for_status()?
+                .json()?)
+    })?;
+
+    // The first row holds the field names.
+    Ok(rows
+        .drain(..)
+        .skip(1)
+        .filter_map(|mut row| {
+            let original = row.pop()?;
+            let timestamp = row.pop()?;
+            Some(Snapshot { timestamp, original })
+        })
+        .collect())
+}
+
+// Reduces a post's URL to the parts that identify it, so that a feed's links and the CDX API's
+// captures of the same page compare equal despite differences in scheme, default port, "www."
+// or trailing slash.
+fn normalize_link(url: &str) -> String {
+    let Ok(parsed) = Url::parse(url) else { return url.to_string() };
+    let host = parsed.host_str().unwrap_or_default();
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let port = match parsed.port() {
+        Some(port) if port != 80 && port != 443 => format!(":{port}"),
+        _ => String::new(),
+    };
+    let query = parsed.query().map(|q| format!("?{q}")).unwrap_or_default();
+    format!("{}{}{}{}", host, port, parsed.path().trim_end_matches('/'), query)
+}
+
+fn fetch_once(client: &Client, url: &str) -> anyhow::Result<Vec<u8>> {
+    let resp = client.get(url).send()?.error_for_status()?;
+    Ok(resp.bytes()?.to_vec())
+}
+
+fn html_published(html: &str) -> Option<DateTime<FixedOffset>> {
+    lazy_static! {
+        static ref TIME: Regex =
+            Regex::new(r#"(?is)<time\b[^>]*datetime\s*=\s*["']([^"']+)["']"#).unwrap();
+    };
+    meta_content(html, "article:published_time")
+        .or_else(|| TIME.captures(html).map(|c| c[1].to_string()))
+        .and_then(|d| parse_datetime(&d))
+}
+
+fn html_body(html: &str) -> Option<String> {
+    lazy_static! {
+        static ref ARTICLE: Regex = Regex::new(r"(?is)<article\b[^>]*>(.*)</article>").unwrap();
+        static ref BODY: Regex = Regex::new(r"(?is)<body\b[^>]*>(.*)</body>").unwrap();
+    };
+    ARTICLE
+        .captures(html)
+        .or_else(|| BODY.captures(html))
+        .map(|c| c[1].to_string())
+}
+
+// Rebuilds a blog from the Internet Archive's captures of its feed and post pages. This needs one
+// request per capture, so it is never chosen automatically.
+pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str)
+    -> anyhow::Result<Box<dyn Blog + 'a>>
+{
+    let site_url = Url::parse(url)?;
+    let home_path = site_url.path().trim_end_matches('/');
+    let url_prefix = format!(
+        "{}{}",
+        site_url.host_str().ok_or_else(|| anyhow::anyhow!("URL has no host"))?,
+        home_path
+    );
+
+    let feed_snapshots = query_cdx(config, client, &url_prefix, ".*(rss|atom|xml).*", "digest")?;
+    let page_snapshots = query_cdx(config, client, &url_prefix, "text/html", "urlkey")?;
+    if feed_snapshots.is_empty() && page_snapshots.is_empty() {
+        anyhow::bail!("The Wayback Machine has no captures of {url}");
+    }
+
+    // Name the blog after its most recently archived feed, or its home page if it had no feed.
+    let title = feed_snapshots
+        .iter()
+        .rev()
+        .find_map(|s| {
+            let body = fetch_once(client, &s.raw_url(config)).ok()?;
+            FeedDoc::parse(&body).ok().map(|d| d.title())
+        })
+        .or_else(|| {
+            let home = page_snapshots.iter().find(|s| {
+                Url::parse(&s.original).is_ok_and(|u| u.path().trim_end_matches('/') == home_path)
+            })?;
+            let body = fetch_once(client, &home.raw_url(config)).ok()?;
+            html_title(&String::from_utf8_lossy(&body))
+        })
+        .ok_or_else(|| anyhow::anyhow!("Could not find the title of {url} in the Wayback Machine"))?;
+
+    let key = sanitize_blog_key(&title);
+    let feed_id = format!("{}/{}", config.feed_url_base, key);
+
+    Ok(Box::new(WaybackBlog {
+        url: url.to_string(),
+        title,
+        feed_snapshots,
+        page_snapshots,
+        key,
+        feed_id,
+        config,
+        client,
+    }))
+}
+
+struct WaybackBlog<'a> {
+    url: String,
+    title: String,
+    feed_snapshots: Vec<Snapshot>,
+    page_snapshots: Vec<Snapshot>,
+    key: String,
+    feed_id: String,
+    config: &'a Config,
+    client: &'a Client,
+}
+
+impl Blog for WaybackBlog<'_> {
+    fn blog_type(&self) -> BlogType {
+        BlogType::Wayback
+    }
+
+    fn feed_data(&self) -> FeedData {
+        FeedData {
+            id: self.feed_id.clone(),
+            key: self.key.clone(),
+            title: self.title.clone(),
+            url: self.url.clone(),
+        }
+    }
+
+    fn entries(&self) -> anyhow::Result<Vec<Entry>> {
+        // Keyed by normalized link, so that later captures of a post replace earlier ones, and
+        // page captures of posts already found in the feed are skipped.
+        let mut posts: HashMap<String, Entry> = HashMap::new();
+
+        println!(
+            r#"Scraping "{}" ({} feed captures, {} pages)"#,
+            &self.title, self.feed_snapshots.len(), self.page_snapshots.len()
+        );
+        let pb = init_progress_bar(
+            (self.feed_snapshots.len() + self.page_snapshots.len()).try_into().unwrap()
+        );
+
+        for snapshot in &self.feed_snapshots {
+            pb.inc(1);
+            let doc = self.fetch(snapshot).and_then(|body| FeedDoc::parse(&body));
+            // Archived captures are often truncated or mislabeled, so skip anything unparseable.
+            let Ok(doc) = doc else { continue };
+            for mut entry in doc.entries() {
+                let mut post_key = entry.id.clone();
+                for link in entry.links.iter_mut().filter(|l| l.rel == "alternate") {
+                    post_key = normalize_link(&link.href);
+                    link.href = self.archived_link(&snapshot.timestamp, &link.href);
+                }
+                posts.insert(post_key, entry);
+            }
+        }
+
+        for snapshot in &self.page_snapshots {
+            pb.inc(1);
+            let post_key = normalize_link(&snapshot.original);
+            if posts.contains_key(&post_key) {
+                continue;
+            }
+            let Ok(body) = self.fetch(snapshot) else { continue };
+            if let Some(entry) = self.page_to_entry(snapshot, &String::from_utf8_lossy(&body)) {
+                posts.insert(post_key, entry);
+            }
+        }
+        pb.finish();
+
+        Ok(posts.into_values().collect())
+    }
+}
+
+impl WaybackBlog<'_> {
+    fn fetch(&self, snapshot: &Snapshot) -> anyhow::Result<Vec<u8>> {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        retry_request(self.config, || fetch_once(self.client, &snapshot.raw_url(self.config)))
+    }
+
+    fn archived_link(&self, timestamp: &str, href: &str) -> String {
+        format!("{}/web/{}/{}", self.config.wayback_url, timestamp, href)
+    }
+
+    // Only pages that say when they were published are treated as posts; the rest are indexes,
+    // archives, and other navigation.
+    fn page_to_entry(&self, snapshot: &Snapshot, html: &str) -> Option<Entry> {
+        let published = html_published(html)?;
+        let content = html_body(html).map(|v| {
+            ContentBuilder::default()
+                .value(v)
+                .content_type(Some("html".to_string()))
+                .build()
+        });
+
+        Some(EntryBuilder::default()
+            .title(html_title(html).unwrap_or_default())
+            .id(snapshot.original.clone())
+            .published(Some(published))
+            .updated(snapshot.captured().unwrap_or(published))
+            .content(content)
+            .link(
+                LinkBuilder::default()
+                    .href(self.archived_link(&snapshot.timestamp, &snapshot.original))
+                    .rel("alternate")
+                    .build(),
+            )
+            .build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_server::{serve, TestServer};
+
+    static FEED: &str = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel>
+<title>Example Blog</title><link>http://example.com/</link><description>d</description>
+<item>
+<title>First post</title>
+<link>http://example.com/2010/01/first-post/</link>
+<guid>http://example.com/?p=1</guid>
+<pubDate>Fri, 01 Jan 2010 10:00:00 +0000</pubDate>
+<description>Hello</description>
+</item>
+</channel></rss>"#;
+
+    fn page(title: &str, published: &str) -> String {
+        format!(
+            r#"<html><head><title>{title}</title>
+            <meta property="article:published_time" content="{published}"></head>
+            <body><nav>menu</nav><article><p>{title} body</p></article></body></html>"#
+        )
+    }
+
+    fn mock_archive() -> TestServer {
+        serve(|target| {
+            if target.starts_with("/cdx/search/cdx") {
+                let rows = if target.contains("text%2Fhtml") {
+                    r#"[["timestamp","original"],
+                    ["20100105000000","https://www.example.com:443/2010/01/first-post"],
+                    ["20100106000000","http://example.com/2009/12/page-only/"]]"#
+                } else {
+                    r#"[["timestamp","original"],["20100102000000","http://example.com/feed"]]"#
+                };
+                return Some(rows.to_string());
+            }
+            match target {
+                "/web/20100102000000id_/http://example.com/feed" => Some(FEED.to_string()),
+                "/web/20100105000000id_/https://www.example.com:443/2010/01/first-post" => {
+                    Some(page("First post", "2010-01-01T10:00:00+00:00"))
+                }
+                "/web/20100106000000id_/http://example.com/2009/12/page-only/" => {
+                    Some(page("Page only", "2009-12-31T08:00:00+00:00"))
+                }
+                _ => None,
+            }
+        })
+    }
+
+    fn test_config(server: &TestServer) -> Config {
+        Config {
+            wayback_url: server.url.clone(),
+            feed_url_base: "https://feeds.example".to_string(),
+            max_retries: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn query_cdx_skips_header_row() {
+        let server = mock_archive();
+        let config = test_config(&server);
+        let snapshots =
+            query_cdx(&config, &Client::new(), "example.com", "text/html", "urlkey").unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].timestamp, "20100105000000");
+        assert_eq!(snapshots[1].original, "http://example.com/2009/12/page-only/");
+        let requests = server.requests.lock().unwrap();
+        assert!(requests[0].contains("url=example.com%2F*&"), "{}", requests[0]);
+    }
+
+    #[test]
+    fn normalize_link_ignores_scheme_port_and_www() {
+        let feed_link = normalize_link("http://example.com/2010/01/first-post/");
+        assert_eq!(feed_link, "example.com/2010/01/first-post");
+        assert_eq!(normalize_link("https://www.example.com:443/2010/01/first-post"), feed_link);
+        assert_ne!(normalize_link("http://example.com:8080/2010/01/first-post"), feed_link);
+    }
+
+    #[test]
+    fn rebuilds_feed_and_page_entries() {
+        let server = mock_archive();
+        let config = test_config(&server);
+        let client = Client::new();
+        let blog = get_blog(&config, &client, "http://example.com/").unwrap();
+        assert_eq!(blog.feed_data().title, "Example Blog");
+
+        let mut entries = blog.entries().unwrap();
+        entries.sort_by_key(|e| e.published);
+        assert_eq!(entries.len(), 2);
+
+        let page_only = &entries[0];
+        assert_eq!(page_only.title.value, "Page only");
+        assert_eq!(page_only.published, parse_datetime("2009-12-31T08:00:00+00:00"));
+        let content = page_only.content.as_ref().and_then(|c| c.value.as_deref());
+        assert_eq!(content, Some("<p>Page only body</p>"));
+        assert_eq!(
+            page_only.links[0].href,
+            format!("{}/web/20100106000000/http://example.com/2009/12/page-only/", server.url)
+        );
+
+        // The page capture of the first post is merged with its feed entry, not fetched again.
+        let first = &entries[1];
+        assert_eq!(first.id, "http://example.com/?p=1");
+        assert_eq!(first.published, parse_datetime("2010-01-01T10:00:00+00:00"));
+        assert!(!server.requests.lock().unwrap().iter().any(|r| r.contains("first-post")));
+    }
+}
@@ -1,12 +1,19 @@
 use std::collections::HashMap;
 
-use atom_syndication::{ContentBuilder, Entry, EntryBuilder, LinkBuilder, Person};
+use atom_syndication::{
+    Category, CategoryBuilder, ContentBuilder, Entry, EntryBuilder, LinkBuilder, Person, Text,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
 use reqwest::Url;
 use reqwest::blocking::Client;
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
 
 use crate::common::*;
 
+mod wpcom;
+
 // Parsed from Wordpress API endpoint
 #[derive(Deserialize, Debug)]
 struct WordpressJson {
@@ -17,10 +24,10 @@ struct WordpressJson {
 pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str)
     -> anyhow::Result<Box<dyn Blog + 'a>>
 {
-    // Technically we should use a HEAD request to discover[1] the API base (if it exists), but
-    // this doesn't seem to be enabled on all sites.
-    // [1]: https://developer.wordpress.org/rest-api/using-the-rest-api/discovery/#discovering-the-api
-    let api_url = Url::parse(format!("{url}/wp-json/").as_str())?;
+    let api_url = match discover_api(config, client, url)? {
+        Api::SelfHosted(api_url) => api_url,
+        Api::WordpressCom(site) => return wpcom::get_blog(config, client, &site),
+    };
     let api_json: WordpressJson = retry_request(config, || {
         Ok(client
                 .get(api_url.clone())
@@ -29,38 +36,196 @@ pub fn get_blog<'a>(config: &'a Config, client: &'a Client, url: &str)
                 .json()?)
     })?;
 
+    v2_blog(config, client, api_json, |route| api_endpoint(&api_url, &format!("wp/v2/{route}")))
+}
+
+// Builds a blog on top of the wp/v2 routes, wherever the site happens to serve them.
+fn v2_blog<'a, F>(config: &'a Config, client: &'a Client, api_json: WordpressJson, route: F)
+    -> anyhow::Result<Box<dyn Blog + 'a>>
+where
+    F: Fn(&str) -> anyhow::Result<Url>
+{
     let key = sanitize_blog_key(&api_json.name);
     let feed_id = format!("{}/{}", config.feed_url_base, key);
-    let users_url = api_url.join("wp/v2/users")?;
-    let authors = retry_request(config, || get_users_once(client, &users_url))?;
+    let authors = get_names(
+        config,
+        client,
+        &route("users")?,
+        "users",
+        "falling back to embedded author data",
+    )?;
+    let categories =
+        get_names(config, client, &route("categories")?, "categories", "leaving them out")?;
+    let tags = get_names(config, client, &route("tags")?, "tags", "leaving them out")?;
 
     Ok(Box::new(WordpressBlog {
         api_json,
-        posts_api_url: api_url.join("wp/v2/posts")?,
-        pages_api_url: api_url.join("wp/v2/pages")?,
+        posts_api_url: route("posts")?,
+        pages_api_url: route("pages")?,
+        comments_api_url: route("comments")?,
         key,
         feed_id,
         config,
         client,
         authors,
+        categories,
+        tags,
     }))
 }
 
-fn get_users_once(client: &Client, url: &Url) -> anyhow::Result<HashMap<usize, String>> {
-    let resp = client.get(url.clone()).send()?;
-    let mut users: Vec<User> = resp.error_for_status()?.json()?;
-    Ok(users.drain(..).map(|u| (u.id, u.name)).collect())
+enum Api {
+    // The REST API root of a self-hosted site.
+    SelfHosted(Url),
+    // A site hosted on wordpress.com, identified by its domain.
+    WordpressCom(String),
+}
+
+// Sites on wordpress.com, including those with custom domains, are served through
+// public-api.wordpress.com rather than their own /wp-json/.
+fn is_wordpress_com(site_url: &Url, headers: &reqwest::header::HeaderMap) -> bool {
+    site_url.host_str().is_some_and(|h| h.ends_with(".wordpress.com"))
+        || headers
+            .get("Host-Header")
+            .and_then(|h| h.to_str().ok())
+            .is_some_and(|h| h.eq_ignore_ascii_case("WordPress.com"))
+}
+
+// Finds the REST API root as described in [1]: first from the homepage's Link header, then from
+// its <link> tag, and finally by assuming the API is reachable through the rest_route parameter,
+// which works even when pretty permalinks are disabled.
+// [1]: https://developer.wordpress.org/rest-api/using-the-rest-api/discovery/
+fn discover_api(config: &Config, client: &Client, url: &str) -> anyhow::Result<Api> {
+    lazy_static! {
+        static ref LINK_HEADER: Regex =
+            Regex::new(r#"<([^>]+)>\s*;\s*rel="?https://api\.w\.org/"?"#).unwrap();
+    };
+    let site_url = Url::parse(&format!("{}/", url.trim_end_matches('/')))?;
+
+    let (wordpress_com, link_header, homepage) = retry_request(config, || {
+        let resp = client.get(site_url.clone()).send()?.error_for_status()?;
+        let wordpress_com = is_wordpress_com(&site_url, resp.headers());
+        let link_header = resp
+            .headers()
+            .get_all("Link")
+            .iter()
+            .filter_map(|h| h.to_str().ok())
+            .find_map(|h| LINK_HEADER.captures(h).map(|c| c[1].to_string()));
+        Ok((wordpress_com, link_header, resp.text()?))
+    })?;
+    if wordpress_com {
+        let site = site_url.host_str().ok_or_else(|| anyhow::anyhow!("URL has no host"))?;
+        return Ok(Api::WordpressCom(site.to_string()));
+    }
+    let api_url = link_header
+        .or_else(|| link_href(&homepage, "https://api.w.org/"))
+        .unwrap_or_else(|| String::from("?rest_route=/"));
+
+    Ok(Api::SelfHosted(site_url.join(&api_url)?))
+}
+
+// Builds the URL of an API route. Sites without pretty permalinks expose the API through the
+// rest_route query parameter rather than a path prefix.
+fn api_endpoint(api_url: &Url, route: &str) -> anyhow::Result<Url> {
+    if !api_url.query_pairs().any(|(k, _)| k == "rest_route") {
+        return Ok(api_url.join(route)?);
+    }
+    let mut endpoint = api_url.clone();
+    let pairs: Vec<(String, String)> = api_url
+        .query_pairs()
+        .filter(|(k, _)| k != "rest_route")
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    endpoint
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair("rest_route", &format!("/{route}"));
+    Ok(endpoint)
+}
+
+// Fetches every page of a collection such as users, tags or comments.
+fn get_all<T: DeserializeOwned>(config: &Config, client: &Client, url: &Url)
+    -> anyhow::Result<Vec<T>>
+{
+    let mut items = Vec::new();
+    let mut api_page = 1;
+    loop {
+        let (page_items, num_api_pages) =
+            retry_request(config, || get_all_once(client, url, api_page))?;
+        items.extend(page_items);
+        if api_page >= num_api_pages {
+            break;
+        }
+
+        api_page += 1;
+    }
+    Ok(items)
+}
+
+// Fetches every user or taxonomy term, mapped from ID to name. Many sites hide their user list,
+// and some their taxonomies, so a missing or forbidden page just means doing without the rest,
+// while keeping the names from the pages before it.
+fn get_names(config: &Config, client: &Client, url: &Url, what: &str, fallback: &str)
+    -> anyhow::Result<HashMap<usize, String>>
+{
+    let mut names = HashMap::new();
+    let mut api_page = 1;
+    loop {
+        let resp = retry_request(config, || get_all_once::<Named>(client, url, api_page));
+        let (page_names, num_api_pages) = match resp {
+            Ok(r) => r,
+            Err(e) if is_hidden_error(&e) => {
+                println!("WARNING: can't list {what} ({e}), {fallback}");
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        names.extend(page_names.into_iter().map(|n| (n.id, n.name)));
+        if api_page >= num_api_pages {
+            break;
+        }
+
+        api_page += 1;
+    }
+    Ok(names)
+}
+
+fn is_hidden_error(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<reqwest::Error>()
+        .and_then(|re| re.status())
+        .is_some_and(|s| [401, 403, 404].contains(&s.as_u16()))
+}
+
+fn get_all_once<T: DeserializeOwned>(client: &Client, url: &Url, page: usize)
+    -> anyhow::Result<(Vec<T>, usize)>
+{
+    let resp = client
+        .get(url.clone())
+        .query(&[("per_page", "100"), ("page", &format!("{page}"))])
+        .send()?
+        .error_for_status()?;
+    let pages = resp
+        .headers()
+        .get("X-WP-TotalPages")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.parse::<usize>().ok())
+        .unwrap_or(1);
+
+    Ok((resp.json()?, pages))
 }
 
 struct WordpressBlog<'a> {
     api_json: WordpressJson,
     posts_api_url: Url,
     pages_api_url: Url,
+    comments_api_url: Url,
     key: String,
     feed_id: String,
     config: &'a Config,
     client: &'a Client,
     authors: HashMap<usize, String>,
+    categories: HashMap<usize, String>,
+    tags: HashMap<usize, String>,
 }
 
 impl Blog for WordpressBlog<'_> {
@@ -94,7 +259,7 @@ impl Blog for WordpressBlog<'_> {
                 }
                 if let Some(pb) = pb.as_ref() { pb.inc(tmp_posts.len().try_into().unwrap()) };
                 posts.extend(tmp_posts.iter().map(|p| self.post_to_entry(p)));
-                if api_page == num_api_pages || posts.len() == num_posts {
+                if api_page >= num_api_pages {
                     break;
                 }
 
@@ -105,13 +270,38 @@ impl Blog for WordpressBlog<'_> {
 
         Ok(posts)
     }
+
+    fn supports_comments(&self) -> bool {
+        true
+    }
+
+    fn comments(&self, entry: &Entry) -> anyhow::Result<Vec<Comment>> {
+        let Some(post_id) = post_id_from_entry(&self.feed_id, entry) else {
+            return Ok(Vec::new());
+        };
+        let mut url = self.comments_api_url.clone();
+        url.query_pairs_mut().append_pair("post", post_id);
+        // Sites can close comments to anonymous readers entirely.
+        let comments: Vec<WordpressComment> = match get_all(self.config, self.client, &url) {
+            Err(e) if is_hidden_error(&e) => Vec::new(),
+            comments => comments?,
+        };
+
+        Ok(comments.iter().map(WordpressComment::to_comment).collect())
+    }
 }
 
 impl WordpressBlog<'_> {
     fn get_page_once(&self, api_url: &Url, page: usize)
         -> anyhow::Result<(Vec<Post>, usize, usize)>
     {
-        let req = self.client.get(api_url.clone()).query(&[("page", &format!("{page}"))]);
+        let req = self
+            .client
+            .get(api_url.clone())
+            .query(&[
+                ("page", &format!("{page}")),
+                ("_embed", &String::from("author,wp:featuredmedia")),
+            ]);
         let resp = req.send()?.error_for_status()?;
 
         let items = resp
@@ -131,29 +321,65 @@ impl WordpressBlog<'_> {
         Ok((posts, items, pages))
     }
 
+    fn author_name(&self, post: &Post) -> String {
+        self.authors
+            .get(&post.author)
+            .cloned()
+            .or_else(|| post.embedded.author.first().and_then(|a| a.name.clone()))
+            .unwrap_or_else(|| format!("User {}", post.author))
+    }
+
+    // Terms missing from the lookups were probably deleted or hidden since, so they're skipped.
+    fn post_categories(&self, post: &Post) -> Vec<Category> {
+        post.categories
+            .iter()
+            .filter_map(|id| self.categories.get(id))
+            .chain(post.tags.iter().filter_map(|id| self.tags.get(id)))
+            .map(|name| CategoryBuilder::default().term(name.clone()).build())
+            .collect()
+    }
+
     fn post_to_entry(&self, post: &Post) -> Entry {
         let content = ContentBuilder::default()
             .value(post.content.rendered.clone())
             .content_type(Some("html".to_string()))
             .build();
+        let published = parse_assuming_utc(&post.date_gmt);
+        // Password-protected posts have an empty excerpt.
+        let summary = post
+            .excerpt
+            .as_ref()
+            .filter(|e| !e.rendered.trim().is_empty())
+            .map(|e| Text::html(e.rendered.clone()));
+        let mut links = vec![
+            LinkBuilder::default()
+                .href(post.link.clone())
+                .rel("alternate")
+                .build(),
+        ];
+        links.extend(post.embedded.featured_media.iter().find_map(|m| {
+            m.source_url.as_ref().map(|url| image_enclosure(url, m.mime_type.as_deref()))
+        }));
 
-        EntryBuilder::default()
+        let mut entry = EntryBuilder::default()
             .title(post.title.rendered.clone())
             .id(format!("{}/{}", self.feed_id, post.id))
-            .published(parse_assuming_utc(&post.date_gmt))
+            .published(published)
             .author(Person {
-                name: self.authors[&post.author].clone(),
+                name: self.author_name(post),
                 email: None,
                 uri: None,
             })
+            .categories(self.post_categories(post))
+            .summary(summary)
             .content(content)
-            .link(
-                LinkBuilder::default()
-                    .href(post.link.clone())
-                    .rel("alternate")
-                    .build(),
-            )
-            .build()
+            .links(links)
+            .build();
+        let updated = post.modified_gmt.as_deref().and_then(parse_assuming_utc);
+        if let Some(updated) = updated.or(published) {
+            entry.set_updated(updated);
+        }
+        entry
     }
 }
 
@@ -162,18 +388,105 @@ struct Content {
     rendered: String,
 }
 
+// A user or taxonomy term.
 #[derive(Deserialize, Debug)]
-struct User {
+struct Named {
     id: usize,
     name: String,
 }
 
+// Embedded authors are error objects without a name when the site hides its users.
+#[derive(Deserialize, Debug)]
+struct EmbeddedAuthor {
+    name: Option<String>,
+}
+
+// Like authors, media the reader can't see is embedded as an error object.
+#[derive(Deserialize, Debug)]
+struct EmbeddedMedia {
+    source_url: Option<String>,
+    mime_type: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Embedded {
+    #[serde(default)]
+    author: Vec<EmbeddedAuthor>,
+    #[serde(rename = "wp:featuredmedia", default)]
+    featured_media: Vec<EmbeddedMedia>,
+}
+
 #[derive(Deserialize, Debug)]
 struct Post {
     id: usize,
     date_gmt: String,
+    modified_gmt: Option<String>,
     link: String,
     title: Content,
     content: Content,
+    excerpt: Option<Content>,
     author: usize,
+    // Pages have no taxonomy.
+    #[serde(default)]
+    categories: Vec<usize>,
+    #[serde(default)]
+    tags: Vec<usize>,
+    #[serde(rename = "_embedded", default)]
+    embedded: Embedded,
+}
+
+#[derive(Deserialize, Debug)]
+struct WordpressComment {
+    id: usize,
+    // Zero for top-level comments.
+    parent: usize,
+    author_name: String,
+    #[serde(default)]
+    author_url: String,
+    date_gmt: String,
+    link: Option<String>,
+    content: Content,
+}
+
+impl WordpressComment {
+    fn to_comment(&self) -> Comment {
+        Comment {
+            id: self.id.to_string(),
+            parent: (self.parent != 0).then(|| self.parent.to_string()),
+            author: self.author_name.clone(),
+            published: parse_assuming_utc(&self.date_gmt),
+            author_url: Some(self.author_url.clone()).filter(|u| !u.is_empty()),
+            link: self.link.clone(),
+            content: self.content.rendered.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_server::{serve_responses, TestResponse};
+
+    #[test]
+    fn get_names_keeps_pages_before_a_hidden_one() {
+        let server = serve_responses(|target| {
+            let page = |n| TestResponse::ok(format!(r#"[{{"id": {n}, "name": "User {n}"}}]"#))
+                .header("X-WP-TotalPages", "3");
+            match target {
+                t if t.starts_with("/users?") && t.ends_with("page=1") => page(1),
+                t if t.starts_with("/users?") => TestResponse::status(403),
+                t if t.starts_with("/tags?") && t.ends_with("page=1") => page(1),
+                _ => TestResponse::status(500),
+            }
+        });
+        let config = Config { max_retries: 1, ..Default::default() };
+        let client = Client::new();
+        let base = Url::parse(&server.url).unwrap();
+
+        let users = get_names(&config, &client, &base.join("users").unwrap(), "users", "")
+            .unwrap();
+        assert_eq!(users, HashMap::from([(1, "User 1".to_string())]));
+        // Other errors still fail the scrape.
+        assert!(get_names(&config, &client, &base.join("tags").unwrap(), "tags", "").is_err());
+    }
 }
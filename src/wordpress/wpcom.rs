@@ -0,0 +1,237 @@
+use atom_syndication::{
+    CategoryBuilder, ContentBuilder, Entry, EntryBuilder, LinkBuilder, Person, Text,
+};
+use reqwest::Url;
+use reqwest::blocking::Client;
+use serde::Deserialize;
+
+use crate::common::*;
+use super::{v2_blog, WordpressJson};
+
+static WPCOM_API: &str = "https://public-api.wordpress.com";
+
+// Largest page size allowed by the v1.1 posts endpoint.
+static V1_PAGE_SIZE: usize = 100;
+
+pub fn get_blog<'a>(config: &'a Config, client: &'a Client, site: &str)
+    -> anyhow::Result<Box<dyn Blog + 'a>>
+{
+    let site_api_url = Url::parse(&format!("{WPCOM_API}/rest/v1.1/sites/{site}/"))?;
+    let site_json: SiteJson = retry_request(config, || {
+        Ok(client
+                .get(site_api_url.clone())
+                .send()?
+                .error_for_status()?
+                .json()?)
+    })?;
+    let api_json = WordpressJson {
+        name: site_json.name,
+        home: site_json.url,
+    };
+
+    // Prefer the wp/v2 namespace, which behaves like a self-hosted site's API. Some sites don't
+    // serve it though, so fall back to the older v1.1 API.
+    let namespace_url = Url::parse(&format!("{WPCOM_API}/wp/v2/sites/{site}/"))?;
+    match retry_request(config, || probe_v2_once(client, &namespace_url)) {
+        Ok(()) => v2_blog(config, client, api_json, |route| Ok(namespace_url.join(route)?)),
+        Err(e) => {
+            println!("WARNING: wp/v2 API unavailable ({e}), falling back to the v1.1 API");
+            let key = sanitize_blog_key(&api_json.name);
+            let feed_id = format!("{}/{}", config.feed_url_base, key);
+            Ok(Box::new(WpcomBlog {
+                api_json,
+                posts_api_url: site_api_url.join("posts/")?,
+                key,
+                feed_id,
+                config,
+                client,
+            }))
+        }
+    }
+}
+
+fn probe_v2_once(client: &Client, namespace_url: &Url) -> anyhow::Result<()> {
+    let resp = client
+        .get(namespace_url.join("posts")?)
+        .query(&[("per_page", "1")])
+        .send()?
+        .error_for_status()?;
+    if !resp.headers().contains_key("X-WP-Total") {
+        anyhow::bail!("Missing expected X-WP-Total header");
+    }
+    Ok(())
+}
+
+// A blog read through the v1.1 API, which pages with an opaque handle instead of page numbers.
+struct WpcomBlog<'a> {
+    api_json: WordpressJson,
+    posts_api_url: Url,
+    key: String,
+    feed_id: String,
+    config: &'a Config,
+    client: &'a Client,
+}
+
+impl Blog for WpcomBlog<'_> {
+    fn blog_type(&self) -> BlogType {
+        BlogType::Wordpress
+    }
+
+    fn feed_data(&self) -> FeedData {
+        FeedData {
+            id: self.feed_id.clone(),
+            key: self.key.clone(),
+            title: self.api_json.name.clone(),
+            url: self.api_json.home.clone(),
+        }
+    }
+
+    fn entries(&self) -> anyhow::Result<Vec<Entry>> {
+        let mut posts: Vec<Entry> = Vec::new();
+
+        for (post_type, display) in &[("post", "posts"), ("page", "pages")] {
+            let mut page_handle: Option<String> = None;
+            let mut pb: Option<indicatif::ProgressBar> = None;
+            loop {
+                let mut resp = retry_request(self.config, || {
+                    self.get_page_once(post_type, page_handle.as_ref())
+                })?;
+                if pb.is_none() {
+                    println!(r#"Scraping "{}" ({} {})"#, &self.api_json.name, resp.found, display);
+                    pb = Some(init_progress_bar(resp.found.try_into().unwrap()));
+                }
+                if let Some(pb) = pb.as_ref() { pb.inc(resp.posts.len().try_into().unwrap()) };
+                posts.extend(resp.posts.iter().map(|p| self.post_to_entry(p)));
+
+                page_handle = resp.meta.next_page.take();
+                if page_handle.is_none() || resp.posts.is_empty() {
+                    break;
+                }
+            }
+            if let Some(pb) = pb { pb.finish() };
+        }
+
+        Ok(posts)
+    }
+}
+
+impl WpcomBlog<'_> {
+    fn get_page_once(&self, post_type: &str, page_handle: Option<&String>)
+        -> anyhow::Result<ListPostsResponse>
+    {
+        let req = self.client.get(self.posts_api_url.clone()).query(&[
+            ("type", post_type),
+            ("number", &format!("{V1_PAGE_SIZE}")),
+            ("order_by", "date"),
+            ("order", "DESC"),
+        ]);
+
+        let req = if let Some(handle) = page_handle {
+            req.query(&[("page_handle", handle)])
+        } else {
+            req
+        };
+
+        Ok(req.send()?.error_for_status()?.json()?)
+    }
+
+    fn post_to_entry(&self, post: &Post) -> Entry {
+        let content = ContentBuilder::default()
+            .value(post.content.clone())
+            .content_type(Some("html".to_string()))
+            .build();
+        let published = parse_datetime(&post.date);
+        let summary = Some(&post.excerpt)
+            .filter(|e| !e.trim().is_empty())
+            .map(|e| Text::html(e.clone()));
+        let mut links = vec![
+            LinkBuilder::default()
+                .href(post.url.clone())
+                .rel("alternate")
+                .build(),
+        ];
+        if !post.featured_image.is_empty() {
+            links.push(image_enclosure(&post.featured_image, None));
+        }
+
+        let mut entry = EntryBuilder::default()
+            .title(post.title.clone())
+            .id(format!("{}/{}", self.feed_id, post.id))
+            .published(published)
+            .author(Person {
+                name: post.author.name.clone(),
+                email: None,
+                uri: post.author.url.clone().filter(|u| !u.is_empty()),
+            })
+            .categories(
+                term_names(&post.categories)
+                    .chain(term_names(&post.tags))
+                    .map(|t| CategoryBuilder::default().term(t.clone()).build())
+                    .collect::<Vec<_>>(),
+            )
+            .summary(summary)
+            .content(content)
+            .links(links)
+            .build();
+        if let Some(updated) = parse_datetime(&post.modified).or(published) {
+            entry.set_updated(updated);
+        }
+        entry
+    }
+}
+
+// The v1.1 API returns taxonomies as objects keyed by term name, or as an empty array.
+fn term_names(terms: &serde_json::Value) -> impl Iterator<Item = &String> {
+    terms.as_object().into_iter().flat_map(|t| t.keys())
+}
+
+#[derive(Deserialize, Debug)]
+struct SiteJson {
+    name: String,
+    #[serde(rename = "URL")]
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Author {
+    name: String,
+    #[serde(rename = "URL")]
+    url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Post {
+    #[serde(rename = "ID")]
+    id: u64,
+    date: String,
+    #[serde(default)]
+    modified: String,
+    #[serde(rename = "URL")]
+    url: String,
+    title: String,
+    content: String,
+    #[serde(default)]
+    excerpt: String,
+    // Empty when the post has no featured image.
+    #[serde(default)]
+    featured_image: String,
+    author: Author,
+    #[serde(default)]
+    categories: serde_json::Value,
+    #[serde(default)]
+    tags: serde_json::Value,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Meta {
+    next_page: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ListPostsResponse {
+    found: usize,
+    #[serde(default)]
+    posts: Vec<Post>,
+    #[serde(default)]
+    meta: Meta,
+}